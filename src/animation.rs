@@ -0,0 +1,88 @@
+use std::num::NonZeroU8;
+
+use crate::{Board, MoveOutcome};
+
+/// How a tile is drawn in one frame of an animation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Effect {
+    #[default]
+    None,
+    /// Just created by a merge.
+    Pulse,
+    /// Just spawned; `true` while still small.
+    Pop(bool),
+}
+
+pub type AnimatedCell = Option<(NonZeroU8, Effect)>;
+
+/// Frames replaying a `MoveOutcome`: tiles first slide cell by cell to
+/// their destination, then merged tiles pulse while the spawned tile
+/// pops in.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    outcome: MoveOutcome,
+    slide_frames: u32,
+    effect_frames: u32,
+}
+
+const SLIDE_MILLIS: u32 = 90;
+const EFFECT_MILLIS: u32 = 90;
+
+impl Animation {
+    pub const DEFAULT_FPS: u32 = 60;
+
+    /// Spreads the animation over as many frames as `fps` allows.
+    pub fn new(outcome: MoveOutcome, fps: u32) -> Self {
+        Self {
+            outcome,
+            slide_frames: (fps.saturating_mul(SLIDE_MILLIS) / 1000).max(1),
+            effect_frames: (fps.saturating_mul(EFFECT_MILLIS) / 1000).max(1),
+        }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.slide_frames + self.effect_frames
+    }
+
+    /// The cells to draw at `frame` for the move that led to `board`.
+    pub fn cells(&self, board: &Board, frame: u32) -> Vec<Vec<AnimatedCell>> {
+        let mut cells = vec![vec![None; board.width()]; board.height()];
+
+        if frame < self.slide_frames {
+            let progress = (frame + 1) as isize;
+            let frames = self.slide_frames as isize;
+            let lerp = |from: usize, to: usize| {
+                (from as isize + (to as isize - from as isize) * progress / frames) as usize
+            };
+            for tile in &self.outcome.moves {
+                let (x, y) = (lerp(tile.from.0, tile.to.0), lerp(tile.from.1, tile.to.1));
+                cells[x][y] = Some((tile.exponent, Effect::None));
+            }
+            return cells;
+        }
+
+        let effect_frame = frame - self.slide_frames;
+        for (x, row) in board.board.iter().enumerate() {
+            for (y, &tile) in row.iter().enumerate() {
+                cells[x][y] = tile.map(|tile| (tile, Effect::None));
+            }
+        }
+        for merge in &self.outcome.merges {
+            let (x, y) = merge.at;
+            cells[x][y] = Some((merge.exponent, Effect::Pulse));
+        }
+        if let Some(spawn) = self.outcome.spawn {
+            let (x, y) = spawn.at;
+            let small = effect_frame * 2 < self.effect_frames;
+            cells[x][y] = Some((spawn.exponent, Effect::Pop(small)));
+        }
+        cells
+    }
+}
+
+/// One frame of an `Animation`, ready to hand to `print_board`.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationFrame<'a> {
+    pub animation: &'a Animation,
+    pub frame: u32,
+}
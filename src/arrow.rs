@@ -1,7 +1,9 @@
+use anyhow::{bail, Error, Result};
 use crossterm::event::KeyCode;
-use anyhow::Result;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
 pub enum Arrow {
     Up,
     Down,
@@ -13,6 +15,41 @@ impl Arrow {
     pub fn iter() -> [Self; 4] {
         [Arrow::Up, Arrow::Down, Arrow::Left, Arrow::Right]
     }
+
+    /// `(row, column)` step a tile takes when moving this way.
+    pub(crate) fn offset(self) -> (isize, isize) {
+        match self {
+            Arrow::Up => (-1, 0),
+            Arrow::Down => (1, 0),
+            Arrow::Left => (0, -1),
+            Arrow::Right => (0, 1),
+        }
+    }
+}
+
+impl fmt::Display for Arrow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Arrow::Up => "up",
+            Arrow::Down => "down",
+            Arrow::Left => "left",
+            Arrow::Right => "right",
+        })
+    }
+}
+
+impl FromStr for Arrow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "up" => Arrow::Up,
+            "down" => Arrow::Down,
+            "left" => Arrow::Left,
+            "right" => Arrow::Right,
+            _ => bail!("unknown direction: {s} (expected up, down, left or right)"),
+        })
+    }
 }
 
 impl TryFrom<KeyCode> for Arrow {
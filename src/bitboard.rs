@@ -0,0 +1,233 @@
+use std::num::NonZeroU8;
+use std::sync::OnceLock;
+
+use anyhow::{ensure, Error, Result};
+use rand::Rng;
+
+use crate::{Arrow, Board, SPAWN_FOUR_ONE_IN};
+
+/// A 4x4 board packed into a `u64`, 4 bits per cell.
+///
+/// Row `r` occupies bits `16 * r..16 * (r + 1)` and column `c` the nibble
+/// at `4 * c` inside it. A nibble holds the tile exponent, 0 being empty,
+/// so tiles above 2^15 cannot be represented. Moves are table lookups per
+/// row, which makes this the representation of choice for search and
+/// simulation. The score is not part of the packing; `shift` returns the
+/// points gained instead.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct BitBoard(u64);
+
+const ROW_MASK: u64 = 0xffff;
+const MAX_EXPONENT: u8 = 15;
+
+struct Tables {
+    left: Box<[u16]>,
+    right: Box<[u16]>,
+    score: Box<[u32]>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let rows = 0..=u16::MAX;
+        Tables {
+            left: rows.clone().map(|row| slide_row(row).0).collect(),
+            right: rows
+                .clone()
+                .map(|row| reverse_row(slide_row(reverse_row(row)).0))
+                .collect(),
+            score: rows.map(|row| slide_row(row).1).collect(),
+        }
+    })
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00f0) | ((row << 4) & 0x0f00) | (row << 12)
+}
+
+/// Slides a packed row towards column 0, returning the new row and the
+/// points scored by its merges.
+fn slide_row(row: u16) -> (u16, u32) {
+    let tiles = (0..4)
+        .map(|c| (row >> (4 * c)) as u8 & 0xf)
+        .filter(|&exp| exp != 0)
+        .collect::<Vec<_>>();
+
+    let (mut slid, mut score) = (Vec::with_capacity(4), 0);
+    let mut i = 0;
+    while i < tiles.len() {
+        let exp = tiles[i];
+        if tiles.get(i + 1) == Some(&exp) && exp < MAX_EXPONENT {
+            slid.push(exp + 1);
+            score += 1 << (exp + 1);
+            i += 2;
+        } else {
+            slid.push(exp);
+            i += 1;
+        }
+    }
+
+    let row = slid
+        .into_iter()
+        .enumerate()
+        .fold(0, |row, (c, exp)| row | (exp as u16) << (4 * c));
+    (row, score)
+}
+
+/// Swaps rows and columns, first inside each 2x2 block, then the blocks.
+fn transpose(x: u64) -> u64 {
+    let a1 = x & 0xf0f0_0f0f_f0f0_0f0f;
+    let a2 = x & 0x0000_f0f0_0000_f0f0;
+    let a3 = x & 0x0f0f_0000_0f0f_0000;
+    let a = a1 | (a2 << 12) | (a3 >> 12);
+    let b1 = a & 0xff00_ff00_00ff_00ff;
+    let b2 = a & 0x00ff_00ff_0000_0000;
+    let b3 = a & 0x0000_0000_ff00_ff00;
+    b1 | (b2 >> 24) | (b3 << 24)
+}
+
+impl BitBoard {
+    pub fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    pub fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Exponent of the tile at `(row, column)`, 0 when empty.
+    pub fn get(self, row: usize, column: usize) -> u8 {
+        (self.0 >> Self::shift_of(row, column)) as u8 & 0xf
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, exponent: u8) {
+        debug_assert!(exponent <= MAX_EXPONENT);
+        let shift = Self::shift_of(row, column);
+        self.0 = (self.0 & !(0xf << shift)) | (exponent as u64) << shift;
+    }
+
+    fn shift_of(row: usize, column: usize) -> u32 {
+        debug_assert!(row < 4 && column < 4);
+        (16 * row + 4 * column) as u32
+    }
+
+    pub fn count_empty(self) -> u32 {
+        (0..16).filter(|i| (self.0 >> (4 * i)) & 0xf == 0).count() as _
+    }
+
+    pub fn max_exponent(self) -> u8 {
+        (0..16)
+            .map(|i| (self.0 >> (4 * i)) as u8 & 0xf)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Slides every tile towards `direction` without spawning,
+    /// returning the new board and the points gained.
+    pub fn shift(self, direction: Arrow) -> (Self, u64) {
+        let tables = tables();
+        let (board, table) = match direction {
+            Arrow::Left => (self.0, &tables.left),
+            Arrow::Right => (self.0, &tables.right),
+            Arrow::Up => (transpose(self.0), &tables.left),
+            Arrow::Down => (transpose(self.0), &tables.right),
+        };
+
+        let (mut shifted, mut score) = (0, 0);
+        for r in 0..4 {
+            let row = ((board >> (16 * r)) & ROW_MASK) as u16;
+            shifted |= (table[row as usize] as u64) << (16 * r);
+            // a row scores the same whichever way its tiles pair up
+            let scored = match direction {
+                Arrow::Left | Arrow::Up => tables.score[row as usize],
+                Arrow::Right | Arrow::Down => tables.score[reverse_row(row) as usize],
+            };
+            score += scored as u64;
+        }
+
+        let shifted = match direction {
+            Arrow::Left | Arrow::Right => shifted,
+            Arrow::Up | Arrow::Down => transpose(shifted),
+        };
+        (Self(shifted), score)
+    }
+
+    pub fn can_move(self, direction: Arrow) -> bool {
+        self.shift(direction).0 != self
+    }
+
+    pub fn legal_moves(self) -> impl Iterator<Item = Arrow> {
+        Arrow::iter()
+            .into_iter()
+            .filter(move |&direction| self.can_move(direction))
+    }
+
+    pub fn is_lost(self) -> bool {
+        self.legal_moves().next().is_none()
+    }
+
+    /// Places a 2 (90%) or a 4 (10%) on a random empty cell,
+    /// following `Board::gen_num`.
+    pub fn gen_num<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let empty = self.count_empty();
+        if empty == 0 {
+            return false;
+        }
+
+        let mut nth = rng.gen_range(0..empty);
+        let exponent = if rng.gen_ratio(1, SPAWN_FOUR_ONE_IN) {
+            2
+        } else {
+            1
+        };
+        for i in 0..16 {
+            if (self.0 >> (4 * i)) & 0xf == 0 {
+                if nth == 0 {
+                    self.0 |= exponent << (4 * i);
+                    break;
+                }
+                nth -= 1;
+            }
+        }
+        true
+    }
+}
+
+impl TryFrom<&Board> for BitBoard {
+    type Error = Error;
+
+    fn try_from(board: &Board) -> Result<Self> {
+        ensure!(
+            (board.width(), board.height()) == (4, 4),
+            "only 4x4 boards can be packed, got {}x{}",
+            board.width(),
+            board.height()
+        );
+
+        let mut packed = Self::default();
+        for (r, row) in board.board.iter().enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                let exponent = cell.map_or(0, NonZeroU8::get);
+                ensure!(
+                    exponent <= MAX_EXPONENT,
+                    "tile 2^{exponent} does not fit in a nibble"
+                );
+                packed.set(r, c, exponent);
+            }
+        }
+        Ok(packed)
+    }
+}
+
+/// Unpacks into a `Board` with a score of 0.
+impl From<BitBoard> for Board {
+    fn from(packed: BitBoard) -> Self {
+        let mut cells = [[0; 4]; 4];
+        for (r, row) in cells.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = packed.get(r, c);
+            }
+        }
+        cells.into()
+    }
+}
@@ -0,0 +1,660 @@
+//! Command line parsing for the `_2048-rs` binary.
+
+use std::fmt::Display;
+use std::num::NonZeroU8;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use _2048_rs::{Batch, Board, Config, GameRecord, Policy, Solver, UndoBudget, MAX_SIDE, MIN_SIDE};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+
+pub enum Command {
+    Play(Options),
+    Bench {
+        batch: Batch,
+        json: bool,
+    },
+    Replay {
+        path: PathBuf,
+        overrides: Config,
+        no_color: bool,
+    },
+    /// Prints a summary of the finished games.
+    Stats {
+        filter: GameFilter,
+        json: bool,
+        overrides: Config,
+    },
+    /// Writes the stats of every finished game, to stdout by default.
+    Export {
+        format: ExportFormat,
+        output: Option<PathBuf>,
+        filter: GameFilter,
+        overrides: Config,
+    },
+    /// Adds the games of a CSV export to the stats.
+    Import {
+        path: PathBuf,
+        overrides: Config,
+    },
+    /// Prints the solver's move for a board.
+    Solve {
+        board: Board,
+        depth: u32,
+    },
+    /// Prints this text and exits.
+    Help(&'static str),
+    Version,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub seed: Option<u64>,
+    /// Settings given as flags, overriding the config file.
+    pub overrides: Config,
+    pub no_color: bool,
+}
+
+/// Which finished games `stats` and `export` look at.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct GameFilter {
+    pub mode: Option<UndoBudget>,
+    pub size: Option<(usize, usize)>,
+}
+
+impl GameFilter {
+    pub fn matches(&self, game: &GameRecord) -> bool {
+        self.mode.is_none_or(|mode| game.mode == mode.mode())
+            && self.size.is_none_or(|(width, height)| {
+                (game.width as usize, game.height as usize) == (width, height)
+            })
+    }
+}
+
+/// Exit status for arguments that could not be parsed; other errors
+/// exit with 1.
+pub const EXIT_USAGE: u8 = 2;
+
+pub const USAGE: &str = "\
+Usage: _2048-rs [COMMAND] [OPTIONS]
+
+Commands:
+  play     Play a game (the default)
+  replay   Watch a recorded game
+  stats    Show how the finished games went
+  bench    Play games headlessly and report the results
+  export   Write the finished games as CSV or JSON
+  import   Add the games of a CSV export to the stats
+  solve    Print the solver's move for a board
+  help     Show the options of a command
+
+Options:
+  -h, --help     Show this help
+  -V, --version  Show the version
+
+Exit status: 0 on success, 1 on errors, 2 on invalid arguments.
+";
+
+const PLAY_USAGE: &str = "\
+Usage: _2048-rs [play] [OPTIONS]
+
+Options:
+  --seed <n>            Play the game of this seed
+  --size <w>x<h>        Board size, e.g. 5x5 or 5
+  --target <tile>       Tile that wins, e.g. 4096
+  --mode <mode>         hardcore, casual or undo-<n>
+  --undo <n>            Undos allowed per game: a count, hardcore or unlimited
+  --four-chance <p>     Probability that a new tile is a 4
+  --theme <theme>       classic, dark, light, solarized, high-contrast,
+                        deuteranopia, protanopia or tritanopia
+  --colors <depth>      truecolor, 256 or 16
+  --markers             Mark tiles with glyphs as well as colors
+  --no-color            Draw without colors (also set by NO_COLOR)
+  --keys <preset>       arrows, wasd, vim or numpad
+  --animation           Animate moves
+  --no-animation        Don't animate moves
+  --fps <n>             Animation frame rate
+  --no-mouse            Leave the mouse to the terminal
+  --swipe-distance <n>  Columns a drag must cover to move
+  --data-dir <dir>      Where saves, stats and replays are kept
+";
+
+const REPLAY_USAGE: &str = "\
+Usage: _2048-rs replay <file> [OPTIONS]
+
+Options:
+  --theme <theme>   Theme to draw with
+  --colors <depth>  truecolor, 256 or 16
+  --markers         Mark tiles with glyphs as well as colors
+  --no-color        Draw without colors (also set by NO_COLOR)
+";
+
+const STATS_USAGE: &str = "\
+Usage: _2048-rs stats [OPTIONS]
+
+Options:
+  --mode <mode>     Only games of this mode: hardcore, casual or undo-<n>
+  --size <w>x<h>    Only games on boards of this size
+  --json            Print the games as JSON instead
+  --data-dir <dir>  Where the stats are kept
+";
+
+const BENCH_USAGE: &str = "\
+Usage: _2048-rs bench [OPTIONS]
+
+Options:
+  --policy <policy>  random, greedy, corner or solver (default random)
+  --games <n>        Games to play (default 1000)
+  --seed <n>         Seed of the first game (default 0)
+  --size <w>x<h>     Board size (default 4x4)
+  --threads <n>      Threads to play on (default: all cores)
+  --json             Print the report as JSON
+";
+
+const EXPORT_USAGE: &str = "\
+Usage: _2048-rs export [OPTIONS]
+
+Options:
+  --format <format>  csv or json (default csv)
+  --output <file>    Write here instead of to stdout
+  --mode <mode>      Only games of this mode: hardcore, casual or undo-<n>
+  --size <w>x<h>     Only games on boards of this size
+  --data-dir <dir>   Where the stats are kept
+";
+
+const IMPORT_USAGE: &str = "\
+Usage: _2048-rs import <file> [OPTIONS]
+
+Adds the games of a CSV file written by `export` to the stats, skipping
+those already recorded.
+
+Options:
+  --data-dir <dir>  Where the stats are kept
+";
+
+const SOLVE_USAGE: &str = "\
+Usage: _2048-rs solve <board> [OPTIONS]
+
+Prints the solver's move for <board>: up, down, left or right. Rows are
+separated by `/` and tiles by commas, with 0 for an empty cell, e.g.
+`2,0,0,2/0,4,0,0/0,0,8,0/0,0,0,2`. Fails if no move is left.
+
+Options:
+  --depth <n>  Moves to look ahead (default 2)
+";
+
+const COMMANDS: [(&str, &str); 7] = [
+    ("play", PLAY_USAGE),
+    ("replay", REPLAY_USAGE),
+    ("stats", STATS_USAGE),
+    ("bench", BENCH_USAGE),
+    ("export", EXPORT_USAGE),
+    ("import", IMPORT_USAGE),
+    ("solve", SOLVE_USAGE),
+];
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut args = args.peekable();
+    let name = match args.peek().map(String::as_str) {
+        Some("-h" | "--help") => return Ok(Command::Help(USAGE)),
+        Some("-V" | "--version") => return Ok(Command::Version),
+        Some("help") => {
+            args.next();
+            return match args.next() {
+                None => Ok(Command::Help(USAGE)),
+                Some(name) => Ok(Command::Help(usage(&name)?)),
+            };
+        }
+        Some(arg) if !arg.starts_with('-') => args.next().unwrap(),
+        _ => "play".to_owned(),
+    };
+    let usage = usage(&name)?;
+    let args = args.collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help(usage));
+    }
+    let args = args.into_iter();
+    match name.as_str() {
+        "play" => parse_play(args).map(Command::Play),
+        "replay" => parse_replay(args),
+        "stats" => parse_stats(args),
+        "bench" => parse_bench(args),
+        "export" => parse_export(args),
+        "import" => parse_import(args),
+        "solve" => parse_solve(args),
+        _ => unreachable!("usage() knows every command"),
+    }
+}
+
+fn usage(command: &str) -> Result<&'static str> {
+    COMMANDS
+        .iter()
+        .find(|&&(name, _)| name == command)
+        .map(|&(_, usage)| usage)
+        .with_context(|| format!("unknown command: {command}"))
+}
+
+fn parse_play(args: impl Iterator<Item = String>) -> Result<Options> {
+    let mut options = Options::default();
+    for_each_flag(args, |flag, value| {
+        let overrides = &mut options.overrides;
+        match flag {
+            "--seed" => options.seed = Some(parse_value(flag, value()?)?),
+            "--undo" | "--mode" => overrides.undo = Some(value()?.parse()?),
+            "--target" => overrides.target = Some(parse_target(&value()?)?),
+            "--size" => {
+                let (width, height) = parse_board_size(&value()?)?;
+                (overrides.width, overrides.height) = (Some(width), Some(height));
+            }
+            "--four-chance" => {
+                let chance = value()?;
+                match chance.parse() {
+                    Ok(chance) if (0.0..=1.0).contains(&chance) => {
+                        overrides.four_chance = Some(chance)
+                    }
+                    _ => bail!("invalid spawn probability: {chance} (expected 0 to 1)"),
+                }
+            }
+            "--animation" => overrides.animation = Some(true),
+            "--no-animation" => overrides.animation = Some(false),
+            "--fps" => {
+                let fps = value()?;
+                match fps.parse() {
+                    Ok(fps @ 1..=1000) => overrides.fps = Some(fps),
+                    _ => bail!("invalid frame rate: {fps}"),
+                }
+            }
+            "--theme" => overrides.theme = Some(value()?.parse()?),
+            "--colors" => overrides.colors = Some(value()?.parse()?),
+            "--markers" => overrides.markers = Some(true),
+            "--no-color" => options.no_color = true,
+            "--no-mouse" => overrides.mouse = Some(false),
+            "--swipe-distance" => {
+                let distance = value()?;
+                match distance.parse() {
+                    Ok(distance @ 1..=100) => overrides.swipe_distance = Some(distance),
+                    _ => bail!("invalid swipe distance: {distance}"),
+                }
+            }
+            "--keys" => overrides.keys.preset = Some(value()?.parse()?),
+            "--data-dir" => overrides.save_dir = Some(value()?.into()),
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(options)
+}
+
+fn parse_bench(args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut batch = Batch {
+        policy: Policy::Random,
+        games: 1000,
+        seed: 0,
+        width: 4,
+        height: 4,
+        threads: std::thread::available_parallelism().map_or(1, Into::into),
+    };
+    let mut json = false;
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--policy" => batch.policy = value()?.parse()?,
+            "--games" => batch.games = parse_count(flag, value()?)?,
+            "--seed" => batch.seed = parse_value(flag, value()?)?,
+            "--size" => (batch.width, batch.height) = parse_board_size(&value()?)?,
+            "--threads" => batch.threads = parse_count(flag, value()?)?,
+            "--json" => json = true,
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Bench { batch, json })
+}
+
+fn parse_replay(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let path = args.next().context("replay requires a file")?;
+    let mut overrides = Config::default();
+    let mut no_color = false;
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--theme" => overrides.theme = Some(value()?.parse()?),
+            "--colors" => overrides.colors = Some(value()?.parse()?),
+            "--markers" => overrides.markers = Some(true),
+            "--no-color" => no_color = true,
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Replay {
+        path: path.into(),
+        overrides,
+        no_color,
+    })
+}
+
+fn parse_stats(args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut filter = GameFilter::default();
+    let mut json = false;
+    let mut overrides = Config::default();
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--mode" => filter.mode = Some(value()?.parse()?),
+            "--size" => filter.size = Some(parse_board_size(&value()?)?),
+            "--json" => json = true,
+            "--data-dir" => overrides.save_dir = Some(value()?.into()),
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Stats {
+        filter,
+        json,
+        overrides,
+    })
+}
+
+fn parse_export(args: impl Iterator<Item = String>) -> Result<Command> {
+    let mut format = ExportFormat::default();
+    let mut output = None;
+    let mut filter = GameFilter::default();
+    let mut overrides = Config::default();
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--format" => {
+                format = match value()?.as_str() {
+                    "csv" => ExportFormat::Csv,
+                    "json" => ExportFormat::Json,
+                    other => bail!("unknown export format: {other} (expected csv or json)"),
+                }
+            }
+            "--output" => output = Some(value()?.into()),
+            "--mode" => filter.mode = Some(value()?.parse()?),
+            "--size" => filter.size = Some(parse_board_size(&value()?)?),
+            "--data-dir" => overrides.save_dir = Some(value()?.into()),
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Export {
+        format,
+        output,
+        filter,
+        overrides,
+    })
+}
+
+fn parse_import(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let path = args.next().context("import requires a file")?;
+    let mut overrides = Config::default();
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--data-dir" => overrides.save_dir = Some(value()?.into()),
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Import {
+        path: path.into(),
+        overrides,
+    })
+}
+
+fn parse_solve(mut args: impl Iterator<Item = String>) -> Result<Command> {
+    let board = args.next().context("solve requires a board")?;
+    let board = board
+        .parse()
+        .with_context(|| format!("invalid board: {board}"))?;
+    let mut depth = Solver::DEFAULT_DEPTH;
+    for_each_flag(args, |flag, value| {
+        match flag {
+            "--depth" => depth = parse_value(flag, value()?)?,
+            _ => bail!("unknown argument: {flag}"),
+        }
+        Ok(())
+    })?;
+    Ok(Command::Solve { board, depth })
+}
+
+/// Calls `handle` with each flag and a way to fetch its value, accepting
+/// both `--flag value` and `--flag=value`.
+fn for_each_flag(
+    mut args: impl Iterator<Item = String>,
+    mut handle: impl FnMut(&str, &mut dyn FnMut() -> Result<String>) -> Result<()>,
+) -> Result<()> {
+    while let Some(arg) = args.next() {
+        let (flag, mut inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+            None => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .take()
+                .or_else(|| args.next())
+                .with_context(|| format!("{flag} requires a value"))
+        };
+        handle(&flag, &mut value)?;
+    }
+    Ok(())
+}
+
+fn parse_value<T>(flag: &str, value: String) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| anyhow!("invalid value for {flag}: {value} ({e})"))
+}
+
+/// A value for `flag` that must be at least 1.
+fn parse_count<T>(flag: &str, value: String) -> Result<T>
+where
+    T: FromStr + Default + PartialEq,
+    T::Err: Display,
+{
+    let count = parse_value(flag, value)?;
+    ensure!(count != T::default(), "{flag} must be at least 1");
+    Ok(count)
+}
+
+/// Parses a tile value such as `2048` into its exponent.
+fn parse_target(tile: &str) -> Result<NonZeroU8> {
+    match tile.parse::<u64>() {
+        Ok(tile) if tile.is_power_of_two() && tile >= 4 => {
+            Ok(NonZeroU8::new(tile.trailing_zeros() as u8).unwrap())
+        }
+        _ => bail!("invalid target tile: {tile} (expected a power of two, at least 4)"),
+    }
+}
+
+/// Parses `WIDTHxHEIGHT`, or a single number for a square board.
+fn parse_size(size: &str) -> Result<(usize, usize)> {
+    let (width, height) = size.split_once('x').unwrap_or((size, size));
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => bail!("invalid board size: {size} (expected e.g. 4x4 or 5)"),
+    }
+}
+
+/// A size as `parse_size` reads it, checked to be one a game can have.
+fn parse_board_size(size: &str) -> Result<(usize, usize)> {
+    let (width, height) = parse_size(size)?;
+    ensure!(
+        Board::is_valid_size(width, height),
+        "board size {width}x{height} is out of range ({MIN_SIDE}..={MAX_SIDE})"
+    );
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// The message of a parse error, which `main` exits on with `EXIT_USAGE`.
+    fn usage_error(args: &[&str]) -> String {
+        match parse_args(args) {
+            Ok(_) => panic!("{args:?} parsed"),
+            Err(err) => format!("{err:#}"),
+        }
+    }
+
+    #[test]
+    fn test_play_is_the_default() {
+        let Ok(Command::Play(options)) = parse_args(&[]) else {
+            panic!("no arguments should play");
+        };
+        assert_eq!(options.seed, None);
+        assert_eq!(options.overrides, Config::default());
+
+        let Ok(Command::Play(options)) = parse_args(&["--seed", "7", "--no-color"]) else {
+            panic!("flags alone should play");
+        };
+        assert_eq!(options.seed, Some(7));
+        assert!(options.no_color);
+        assert!(matches!(
+            parse_args(&["play", "--seed", "7"]),
+            Ok(Command::Play(_))
+        ));
+    }
+
+    #[test]
+    fn test_inline_values() {
+        let Ok(Command::Play(options)) = parse_args(&[
+            "--seed=7",
+            "--size=5x4",
+            "--mode=undo-3",
+            "--theme",
+            "dark",
+            "--swipe-distance=6",
+        ]) else {
+            panic!("should play");
+        };
+        assert_eq!(options.seed, Some(7));
+        let overrides = options.overrides;
+        assert_eq!((overrides.width, overrides.height), (Some(5), Some(4)));
+        assert_eq!(overrides.undo, Some(UndoBudget::Limited(3)));
+        assert_eq!(overrides.theme, Some("dark".parse().unwrap()));
+        assert_eq!(overrides.swipe_distance, Some(6));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        assert_eq!(usage_error(&["--bogus"]), "unknown argument: --bogus");
+        assert_eq!(
+            usage_error(&["stats", "--bogus"]),
+            "unknown argument: --bogus"
+        );
+        assert_eq!(usage_error(&["frob"]), "unknown command: frob");
+        assert_eq!(usage_error(&["--seed"]), "--seed requires a value");
+        assert_eq!(
+            usage_error(&["export", "--format"]),
+            "--format requires a value"
+        );
+        assert_eq!(usage_error(&["replay"]), "replay requires a file");
+        assert_eq!(usage_error(&["--fps", "0"]), "invalid frame rate: 0");
+        assert_eq!(
+            usage_error(&["--swipe-distance", "0"]),
+            "invalid swipe distance: 0"
+        );
+        assert_eq!(usage_error(&["--fps=1001"]), "invalid frame rate: 1001");
+        assert!(usage_error(&["--size", "9x9"]).contains("out of range"));
+        assert!(usage_error(&["solve", "2,4/4,2"]).starts_with("invalid board"));
+    }
+
+    #[test]
+    fn test_bench_usage_errors() {
+        assert!(usage_error(&["bench", "--size", "9x9"]).contains("out of range"));
+        assert_eq!(
+            usage_error(&["bench", "--games", "0"]),
+            "--games must be at least 1"
+        );
+        assert_eq!(
+            usage_error(&["bench", "--threads=0"]),
+            "--threads must be at least 1"
+        );
+        assert!(matches!(
+            parse_args(&["bench", "--size=3", "--games", "1", "--threads", "1"]),
+            Ok(Command::Bench {
+                batch: Batch {
+                    width: 3,
+                    height: 3,
+                    games: 1,
+                    threads: 1,
+                    ..
+                },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_help() {
+        assert!(matches!(parse_args(&["--help"]), Ok(Command::Help(USAGE))));
+        assert!(matches!(parse_args(&["help"]), Ok(Command::Help(USAGE))));
+        assert!(matches!(parse_args(&["-V"]), Ok(Command::Version)));
+        assert!(matches!(
+            parse_args(&["help", "solve"]),
+            Ok(Command::Help(SOLVE_USAGE))
+        ));
+        assert!(matches!(
+            parse_args(&["stats", "--json", "-h"]),
+            Ok(Command::Help(STATS_USAGE))
+        ));
+        assert_eq!(usage_error(&["help", "frob"]), "unknown command: frob");
+    }
+
+    #[test]
+    fn test_filters() {
+        let Ok(Command::Stats { filter, json, .. }) =
+            parse_args(&["stats", "--mode", "hardcore", "--size=5"])
+        else {
+            panic!("should show stats");
+        };
+        assert!(!json);
+        assert_eq!(
+            filter,
+            GameFilter {
+                mode: Some(UndoBudget::HARDCORE),
+                size: Some((5, 5)),
+            }
+        );
+        let game = GameRecord {
+            played_at: 0,
+            score: 0,
+            max_exponent: 1,
+            target: 11,
+            moves: 0,
+            duration_secs: 0,
+            mode: "hardcore".to_owned(),
+            width: 5,
+            height: 5,
+            seed: 0,
+        };
+        assert!(filter.matches(&game));
+        assert!(!filter.matches(&GameRecord {
+            mode: "casual".to_owned(),
+            ..game.clone()
+        }));
+        assert!(!filter.matches(&GameRecord { width: 4, ..game }));
+
+        let Ok(Command::Export { filter, format, .. }) =
+            parse_args(&["export", "--format=json", "--mode", "undo-2"])
+        else {
+            panic!("should export");
+        };
+        assert_eq!(format, ExportFormat::Json);
+        assert_eq!(filter.mode, Some(UndoBudget::Limited(2)));
+        assert_eq!(filter.size, None);
+    }
+}
@@ -0,0 +1,469 @@
+//! The user's `config.toml`.
+//!
+//! Only the part of TOML a settings file needs is understood: `[table]`
+//! headers, `key = value` pairs with dotted keys, `#` comments, and values
+//! that are strings, integers, floats, booleans, arrays of those, which may
+//! span lines, or inline tables.
+//!
+//! ```toml
+//! theme = "classic"
+//! colors = 256
+//! markers = true
+//! save_dir = "~/games/2048"
+//!
+//! [game]
+//! width = 5
+//! height = 5
+//! target = 4096
+//! undo = "hardcore"
+//! four_chance = 0.1
+//!
+//! [animation]
+//! enabled = true
+//! fps = 60
+//!
+//! mouse = { enabled = true, swipe_distance = 4 }
+//!
+//! [keys]
+//! preset = "vim"
+//! undo = [
+//!     "u",
+//!     "backspace",
+//! ]
+//! ```
+//!
+//! Every setting is optional; command line flags override the file.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::num::NonZeroU8;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::{
+    parse_key, Board, ColorDepth, Colors, KeyConfig, Theme, UndoBudget, MAX_SIDE, MIN_SIDE,
+};
+
+const TABLES: [&str; 4] = ["game", "animation", "mouse", "keys"];
+
+/// Settings read from `config.toml`, unset where the file says nothing.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Config {
+    pub theme: Option<Theme>,
+    /// Colors the terminal supports, detected when unset.
+    pub colors: Option<ColorDepth>,
+    /// Marks tiles with glyphs as well as colors.
+    pub markers: Option<bool>,
+    /// Where saves, stats and replays are kept.
+    pub save_dir: Option<PathBuf>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Exponent of the winning tile.
+    pub target: Option<NonZeroU8>,
+    pub undo: Option<UndoBudget>,
+    /// Probability that a spawned tile is a 4 rather than a 2.
+    pub four_chance: Option<f64>,
+    pub animation: Option<bool>,
+    pub fps: Option<u32>,
+    /// Captures the mouse for swipes and buttons.
+    pub mouse: Option<bool>,
+    /// Columns a drag must cover to make a move.
+    pub swipe_distance: Option<u16>,
+    pub keys: KeyConfig,
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut config = Self::default();
+        let mut table = String::new();
+        let mut seen = HashSet::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let number = 1 + text[..text.len() - rest.len()].matches('\n').count();
+            let parsed = parse_line(rest).and_then(|(line, next)| {
+                rest = next;
+                config.apply(line, &mut table, &mut seen)
+            });
+            parsed.with_context(|| format!("line {number}"))?;
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// These settings, with those set in `overrides` taking precedence.
+    pub fn merge(self, overrides: Config) -> Config {
+        let mut keys = self.keys;
+        keys.preset = overrides.keys.preset.or(keys.preset);
+        keys.bindings.extend(overrides.keys.bindings);
+        Config {
+            theme: overrides.theme.or(self.theme),
+            colors: overrides.colors.or(self.colors),
+            markers: overrides.markers.or(self.markers),
+            save_dir: overrides.save_dir.or(self.save_dir),
+            width: overrides.width.or(self.width),
+            height: overrides.height.or(self.height),
+            target: overrides.target.or(self.target),
+            undo: overrides.undo.or(self.undo),
+            four_chance: overrides.four_chance.or(self.four_chance),
+            animation: overrides.animation.or(self.animation),
+            fps: overrides.fps.or(self.fps),
+            mouse: overrides.mouse.or(self.mouse),
+            swipe_distance: overrides.swipe_distance.or(self.swipe_distance),
+            keys,
+        }
+    }
+
+    /// The theme to draw with, as the terminal can show it.
+    pub fn colors(&self) -> Colors {
+        Colors {
+            theme: self.theme.unwrap_or_default(),
+            depth: self.colors.unwrap_or_else(ColorDepth::detect),
+            markers: self.markers.unwrap_or(false),
+        }
+    }
+
+    /// Applies one line of the file, in the table the last header named.
+    fn apply(&mut self, line: Line, table: &mut String, seen: &mut HashSet<String>) -> Result<()> {
+        match line {
+            Line::Blank => {}
+            Line::Table(name) => {
+                ensure!(TABLES.contains(&name.as_str()), "unknown table [{name}]");
+                ensure!(seen.insert(format!("[{name}]")), "duplicate table [{name}]");
+                *table = name;
+            }
+            Line::Pair(key, value) => {
+                let mut pairs = Vec::new();
+                let table = (!table.is_empty()).then(|| table.clone());
+                flatten(table.into_iter().chain(key).collect(), value, &mut pairs);
+                for (path, value) in pairs {
+                    let name = path.join(".");
+                    ensure!(seen.insert(name.clone()), "duplicate key {name}");
+                    match &path[..] {
+                        [key] => self.set("", key, value)?,
+                        [table, key] => self.set(table, key, value)?,
+                        _ => bail!("unknown key {name}"),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, table: &str, key: &str, value: Value) -> Result<()> {
+        match (table, key) {
+            ("", "theme") => self.theme = Some(value.into_string(key)?.parse()?),
+            ("", "colors") => {
+                self.colors = Some(match value {
+                    Value::Integer(depth) => depth.to_string().parse()?,
+                    value => value.into_string(key)?.parse()?,
+                })
+            }
+            ("", "markers") => self.markers = Some(value.boolean(key)?),
+            ("", "save_dir") => self.save_dir = Some(value.into_string(key)?.into()),
+            ("game", "width") => self.width = Some(side(value.integer(key)?)?),
+            ("game", "height") => self.height = Some(side(value.integer(key)?)?),
+            ("game", "target") => {
+                let tile = value.integer(key)?;
+                ensure!(
+                    tile >= 4 && (tile as u64).is_power_of_two(),
+                    "invalid target tile: {tile} (expected a power of two, at least 4)"
+                );
+                self.target = NonZeroU8::new(tile.trailing_zeros() as u8);
+            }
+            ("game", "undo") => {
+                self.undo = Some(match value {
+                    Value::Integer(count) => UndoBudget::Limited(
+                        count
+                            .try_into()
+                            .with_context(|| format!("invalid undo budget: {count}"))?,
+                    ),
+                    value => value.into_string(key)?.parse()?,
+                })
+            }
+            ("game", "four_chance") => {
+                let chance = value.float(key)?;
+                ensure!(
+                    (0.0..=1.0).contains(&chance),
+                    "four_chance must be between 0 and 1, not {chance}"
+                );
+                self.four_chance = Some(chance);
+            }
+            ("animation", "enabled") => self.animation = Some(value.boolean(key)?),
+            ("animation", "fps") => match value.integer(key)? {
+                fps @ 1..=1000 => self.fps = Some(fps as u32),
+                fps => bail!("invalid frame rate: {fps}"),
+            },
+            ("mouse", "enabled") => self.mouse = Some(value.boolean(key)?),
+            ("mouse", "swipe_distance") => match value.integer(key)? {
+                distance @ 1..=100 => self.swipe_distance = Some(distance as u16),
+                distance => bail!("invalid swipe distance: {distance}"),
+            },
+            ("keys", "preset") => self.keys.preset = Some(value.into_string(key)?.parse()?),
+            ("keys", action) => {
+                let action = action.parse()?;
+                let keys = match value {
+                    Value::Array(keys) => keys,
+                    key => vec![key],
+                };
+                let keys = keys
+                    .into_iter()
+                    .map(|key| parse_key(&key.into_string("keys")?))
+                    .collect::<Result<Vec<_>>>()?;
+                self.keys.bindings.push((action, keys));
+            }
+            ("", key) => bail!("unknown key {key}"),
+            (table, key) => bail!("unknown key {key} in [{table}]"),
+        }
+        Ok(())
+    }
+}
+
+fn side(value: i64) -> Result<usize> {
+    let side = usize::try_from(value).unwrap_or(0);
+    ensure!(
+        Board::is_valid_size(side, MIN_SIDE),
+        "board side {value} is out of range ({MIN_SIDE}..={MAX_SIDE})"
+    );
+    Ok(side)
+}
+
+enum Line {
+    Blank,
+    Table(String),
+    /// A dotted key such as `game.width`, one segment per part.
+    Pair(Vec<String>, Value),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    /// An inline table, `{ key = value, ... }`.
+    Table(Vec<(Vec<String>, Value)>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        })
+    }
+}
+
+impl Value {
+    fn into_string(self, key: &str) -> Result<String> {
+        match self {
+            Value::String(s) => Ok(s),
+            value => bail!("{key} must be a string, not {value}"),
+        }
+    }
+
+    fn integer(&self, key: &str) -> Result<i64> {
+        match *self {
+            Value::Integer(n) => Ok(n),
+            ref value => bail!("{key} must be an integer, not {value}"),
+        }
+    }
+
+    /// A float, or an integer read as one.
+    fn float(&self, key: &str) -> Result<f64> {
+        match *self {
+            Value::Float(x) => Ok(x),
+            Value::Integer(n) => Ok(n as f64),
+            ref value => bail!("{key} must be a number, not {value}"),
+        }
+    }
+
+    fn boolean(&self, key: &str) -> Result<bool> {
+        match *self {
+            Value::Boolean(b) => Ok(b),
+            ref value => bail!("{key} must be true or false, not {value}"),
+        }
+    }
+}
+
+/// Spreads inline tables into one pair for each value they hold.
+fn flatten(path: Vec<String>, value: Value, pairs: &mut Vec<(Vec<String>, Value)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                flatten([path.clone(), key].concat(), value, pairs);
+            }
+        }
+        value => pairs.push((path, value)),
+    }
+}
+
+/// Parses the line at the start of `s`, returning it and the lines after
+/// it, which an array spanning lines takes some of.
+fn parse_line(s: &str) -> Result<(Line, &str)> {
+    let line = trim_space(s);
+    if line.is_empty() || line.starts_with(['#', '\n']) {
+        return Ok((Line::Blank, end_of_line(line)?));
+    }
+    if let Some(rest) = line.strip_prefix('[') {
+        let (name, rest) = parse_dotted_key(rest)?;
+        let rest = rest.strip_prefix(']').context("expected `]`")?;
+        return Ok((Line::Table(name.join(".")), end_of_line(rest)?));
+    }
+    let (key, rest) = parse_dotted_key(line)?;
+    let rest = rest.strip_prefix('=').context("expected `key = value`")?;
+    let (value, rest) = parse_value(trim_space(rest))?;
+    Ok((Line::Pair(key, value), end_of_line(rest)?))
+}
+
+/// Parses the possibly dotted key at the start of `s`, returning its parts
+/// and what follows.
+fn parse_dotted_key(s: &str) -> Result<(Vec<String>, &str)> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    loop {
+        rest = trim_space(rest);
+        let end = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'))
+            .unwrap_or(rest.len());
+        let (part, after) = rest.split_at(end);
+        ensure!(!part.is_empty(), "invalid key: `{}`", first_line(s).trim());
+        parts.push(part.to_owned());
+        rest = trim_space(after);
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after,
+            None => return Ok((parts, rest)),
+        }
+    }
+}
+
+/// Skips what may follow a value, nothing but a comment, returning the
+/// lines after it.
+fn end_of_line(rest: &str) -> Result<&str> {
+    let rest = trim_space(rest);
+    let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+    ensure!(
+        line.is_empty() || line.starts_with('#'),
+        "unexpected `{}`",
+        line.trim_end()
+    );
+    Ok(next)
+}
+
+fn first_line(s: &str) -> &str {
+    s.split('\n').next().unwrap_or_default()
+}
+
+/// Skips spaces, but not the end of the line.
+fn trim_space(s: &str) -> &str {
+    s.trim_start_matches([' ', '\t', '\r'])
+}
+
+/// Skips whitespace and comments across lines, as inside an array.
+fn skip_blank(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start();
+        match s.strip_prefix('#') {
+            Some(comment) => s = comment.split_once('\n').map_or("", |(_, next)| next),
+            None => return s,
+        }
+    }
+}
+
+/// Parses the value at the start of `s`, returning it and what follows.
+fn parse_value(s: &str) -> Result<(Value, &str)> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '"' => return Ok((Value::String(string), &rest[i + 1..])),
+                '\\' => string.push(match chars.next().map(|(_, ch)| ch) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(ch @ ('"' | '\\')) => ch,
+                    Some(ch) => bail!("unknown escape `\\{ch}`"),
+                    None => break,
+                }),
+                '\n' => break,
+                ch => string.push(ch),
+            }
+        }
+        bail!("unterminated string");
+    }
+    if let Some(rest) = s.strip_prefix('\'') {
+        let (string, rest) = first_line(rest)
+            .split_once('\'')
+            .map(|(string, _)| (string, &rest[string.len() + 1..]))
+            .context("unterminated string")?;
+        return Ok((Value::String(string.to_owned()), rest));
+    }
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = skip_blank(rest);
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), rest));
+            }
+            ensure!(!rest.is_empty(), "unterminated array");
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = skip_blank(after);
+            match rest.strip_prefix(',') {
+                Some(after) => rest = after,
+                None if rest.starts_with(']') => {}
+                None => bail!("expected `,` or `]` in array"),
+            }
+        }
+    }
+    if let Some(rest) = s.strip_prefix('{') {
+        let mut pairs = Vec::new();
+        let mut rest = trim_space(rest);
+        if let Some(rest) = rest.strip_prefix('}') {
+            return Ok((Value::Table(pairs), rest));
+        }
+        loop {
+            let (key, after) = parse_dotted_key(rest)?;
+            let after = after.strip_prefix('=').context("expected `key = value`")?;
+            let (value, after) = parse_value(trim_space(after))?;
+            pairs.push((key, value));
+            rest = trim_space(after);
+            if let Some(rest) = rest.strip_prefix('}') {
+                return Ok((Value::Table(pairs), rest));
+            }
+            rest = rest
+                .strip_prefix(',')
+                .context("expected `,` or `}` in inline table")?;
+        }
+    }
+
+    let end = s
+        .find(|ch: char| ch.is_whitespace() || matches!(ch, ',' | ']' | '}' | '#'))
+        .unwrap_or(s.len());
+    let (word, rest) = s.split_at(end);
+    let value = match word {
+        "" => bail!("missing value"),
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        word => {
+            let digits = word.replace('_', "");
+            if let Ok(n) = digits.parse() {
+                Value::Integer(n)
+            } else if let Ok(x) = digits.parse() {
+                Value::Float(x)
+            } else {
+                bail!("invalid value `{word}` (strings need quotes)")
+            }
+        }
+    };
+    Ok((value, rest))
+}
@@ -1,5 +1,4 @@
 use std::io::Stdout;
-use std::num::NonZeroU8;
 
 use ratatui::prelude::*;
 use ratatui::widgets::block::Title;
@@ -7,23 +6,19 @@ use ratatui::widgets::*;
 
 use anyhow::Result;
 
-use crate::Board;
+use crate::animation::{AnimatedCell, AnimationFrame, Effect};
+use crate::font::{big_digits, tile_value};
+use crate::savedata::{Prompt, Purpose, Slot, SlotBrowser};
+use crate::stats::utc_datetime;
+use crate::{Action, Arrow, Board, Colors, GameStatus, Keymap, Stats};
 
-fn color_of(state: NonZeroU8) -> Color {
-    match state.get() % 8 {
-        0 => Color::Rgb(255, 0, 77),
-        1 => Color::Rgb(255, 163, 0),
-        2 => Color::Rgb(255, 240, 36),
-        3 => Color::Rgb(0, 231, 86),
-        4 => Color::Rgb(41, 173, 255),
-        5 => Color::Rgb(131, 118, 156),
-        6 => Color::Rgb(255, 119, 168),
-        7 => Color::Rgb(255, 119, 168),
-        _ => unreachable!(),
-    }
-}
-
-fn board_to_table(board: Board, prev_best: u64, new_best: u64) -> Table<'static> {
+fn board_to_table(
+    board: &Board,
+    cells: Vec<Vec<AnimatedCell>>,
+    layout: BoardLayout,
+    hud: &Hud<'_>,
+) -> Table<'static> {
+    let (prev_best, new_best, colors) = (hud.prev_best, hud.new_best, hud.colors);
     let title = Title::from(format!(
         "{}Score: {}/{new_best}",
         if board.score() > prev_best { "*" } else { "" },
@@ -31,71 +26,692 @@ fn board_to_table(board: Board, prev_best: u64, new_best: u64) -> Table<'static>
     ))
     .alignment(Alignment::Right);
     Table::new(
-        board
-            .board
-            .map(|row| Row::new(row.map(cell_to_widget)).height(CELL_HEIGHT)),
-            &[Constraint::Percentage(25); 4]
+        cells.into_iter().map(|row| {
+            Row::new(
+                row.into_iter()
+                    .map(|cell| cell_to_widget(cell, layout, colors)),
+            )
+            .height(layout.cell_height)
+        }),
+        vec![Constraint::Length(layout.cell_width); board.width()],
     )
     .column_spacing(0)
     .block(
         Block::default()
             .borders(Borders::ALL)
             .title(title)
-            .style(Style::new().bg(Color::DarkGray))
-            .padding(Padding::new(BOARD_HORIZON_PAD + 1, BOARD_HORIZON_PAD, 1, 1)),
+            .border_style(Style::new().fg(colors.border()))
+            .style(Style::new().bg(colors.background()))
+            .padding(Padding::new(
+                BOARD_HORIZON_PAD + 1,
+                BOARD_HORIZON_PAD,
+                BOARD_VERTICAL_PAD,
+                BOARD_VERTICAL_PAD,
+            )),
     )
 }
 
-fn cell_to_widget(cell: Option<NonZeroU8>) -> Cell<'static> {
-    let text = Text::raw(format!(
-        "\n {}",
-        cell.map(|i| 2_u64.saturating_pow(i.get() as _).to_string())
-            .unwrap_or_default(),
-    ));
-    Cell::from(text).style(
-        Style::new()
-            .bg(Color::Black)
-            .fg(cell.map(color_of).unwrap_or(Color::Black)),
-    )
+fn cell_to_widget(cell: AnimatedCell, layout: BoardLayout, colors: Colors) -> Cell<'static> {
+    let Some((tile, effect)) = cell else {
+        return Cell::from("").style(Style::new().bg(colors.empty()));
+    };
+    let value = match effect {
+        Effect::Pop(true) => "·".to_owned(),
+        _ => tile_value(tile.get()),
+    };
+    let (fg, bg) = colors.tile(tile.get());
+    let mut style = match effect {
+        Effect::Pulse => Style::new().bg(fg).fg(bg).add_modifier(Modifier::BOLD),
+        _ => Style::new().bg(bg).fg(fg),
+    };
+    let glyph = match colors.marker(tile.get()) {
+        Some((glyph, modifier)) => {
+            style = style.add_modifier(modifier);
+            glyph
+        }
+        None => ' ',
+    };
+    // block digits where the cell leaves a margin around them,
+    // otherwise plain text, shortened if even that is too wide
+    let (width, height) = (layout.cell_width, layout.cell_height);
+    let rows = big_digits(&value, width.saturating_sub(2), height.saturating_sub(2))
+        .unwrap_or_else(|| match value.len() as u16 {
+            len if len <= width => vec![value],
+            _ => vec![short_tile(tile.get())],
+        });
+    // the value sits in the middle, below the marker in the top corner
+    let mut lines = vec![Line::from(glyph.to_string())];
+    lines.resize(
+        ((height as usize).saturating_sub(rows.len()) / 2).max(1),
+        Line::default(),
+    );
+    lines.extend(
+        rows.into_iter()
+            .map(|row| Line::from(row).alignment(Alignment::Center)),
+    );
+    Cell::from(Text::from(lines)).style(style)
+}
+
+fn still_cells(board: &Board) -> Vec<Vec<AnimatedCell>> {
+    board
+        .board
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|tile| tile.map(|tile| (tile, Effect::None)))
+                .collect()
+        })
+        .collect()
+}
+
+/// What `print_board` shows around the board itself.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hud<'a> {
+    pub status: GameStatus,
+    pub prev_best: u64,
+    pub new_best: u64,
+    /// Undos left in the budget, `None` when unlimited.
+    pub undos_left: Option<u32>,
+    /// The solver's suggestion, once asked for.
+    pub hint: Option<Arrow>,
+    pub autoplay: bool,
+    /// Draws this frame of the last move instead of the settled board.
+    pub animation: Option<AnimationFrame<'a>>,
+    /// An open save or load dialog, drawn over everything else.
+    pub dialog: Option<&'a SlotBrowser>,
+    /// A yes/no question shown over the board.
+    pub question: Option<&'a str>,
+    /// Shown below the board in place of the undo count and hint.
+    pub info: Option<&'a str>,
+    /// The stats screen, drawn over the board.
+    pub stats: Option<&'a Stats>,
+    pub colors: Colors,
+    /// The game's keys, named in the legend below the board and in the
+    /// popups. Without them there is no legend.
+    pub keymap: Option<&'a Keymap>,
+    /// Lists every binding over the board.
+    pub help: bool,
+}
+
+/// Where `print_board` put what can be clicked.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Screen {
+    /// The board with its border.
+    pub board: Rect,
+    pub buttons: Vec<(Rect, Action)>,
+}
+
+impl Screen {
+    /// The buttons of `layout` in a frame of `area`, leaving out those
+    /// past its right edge.
+    pub(crate) fn new(layout: BoardLayout, area: Rect) -> Self {
+        let board = layout.board;
+        let mut x = board.x;
+        let buttons = BUTTONS
+            .iter()
+            .map(|&(label, action)| {
+                let width = label.chars().count() as u16 + 2;
+                let button = Rect {
+                    x,
+                    y: board.bottom() + 2,
+                    width,
+                    height: 1,
+                };
+                x += width + 1;
+                (button, action)
+            })
+            .take_while(|(button, _)| button.right() <= area.right())
+            .collect();
+        Self { board, buttons }
+    }
+
+    /// The action of the button at `(column, row)`, if any.
+    pub fn button(&self, column: u16, row: u16) -> Option<Action> {
+        self.buttons
+            .iter()
+            .find(|(area, _)| contains(*area, column, row))
+            .map(|&(_, action)| action)
+    }
+
+    pub fn on_board(&self, column: u16, row: u16) -> bool {
+        contains(self.board, column, row)
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
 }
 
+/// Draws `board` as large as the terminal allows, centered. Returns
+/// where it went, or `None` if it did not fit and a larger terminal
+/// was asked for instead.
 pub fn print_board(
-    board: Board,
+    board: &Board,
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    lost: bool,
-    prev_best: u64,
-    new_best: u64,
-) -> Result<()> {
+    hud: &Hud<'_>,
+) -> Result<Option<Screen>> {
+    let mut screen = None;
+    let default_keymap = Keymap::default();
+    let keymap = hud.keymap.unwrap_or(&default_keymap);
     terminal.draw(|frame| {
-        let table = board_to_table(board, prev_best, new_best);
+        let Some(layout) = BoardLayout::fit(board, frame.size()) else {
+            let (width, height) = BoardLayout::min_size(board);
+            let message = format!(
+                "Terminal too small\n\nPlease enlarge it to at least {width}x{height}\n(now {}x{})\n\n{}",
+                frame.size().width,
+                frame.size().height,
+                key_hints(keymap, &[(Action::Quit, "quit")]).join("  ")
+            );
+            let area = centered(frame.size(), frame.size().width, 6);
+            frame.render_widget(
+                Paragraph::new(message)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .fg(Color::Yellow),
+                area,
+            );
+            return;
+        };
+        let board_area = layout.board;
+        let (x, board_width) = (board_area.x, board_area.width);
+        let footer = board_area.bottom();
 
-        if lost {
+        let cells = match hud.animation {
+            Some(AnimationFrame { animation, frame }) => animation.cells(board, frame),
+            None => still_cells(board),
+        };
+        let table = board_to_table(board, cells, layout, hud);
+
+        let info = match hud.info {
+            Some(info) => info.to_owned(),
+            None => game_info(hud),
+        };
+        frame.render_widget(
+            Paragraph::new(info).fg(Color::DarkGray),
+            Rect {
+                x,
+                y: footer,
+                width: board_width,
+                height: 1,
+            },
+        );
+
+        if hud.status == GameStatus::Lost {
             let graph = Paragraph::new("You lost!").fg(Color::Red);
             frame.render_widget(
                 graph,
                 Rect {
-                    x: 0,
-                    y: BOARD_HEIGHT + 1,
-                    width: BOARD_WIDTH,
+                    x,
+                    y: footer + 1,
+                    width: board_width,
                     height: 1,
                 },
             );
         }
 
-        frame.render_widget(
-            table,
-            Rect {
-                x: 0,
-                y: 0,
-                width: BOARD_WIDTH,
-                height: BOARD_HEIGHT,
-            },
-        );
+        let buttons = Screen::new(layout, frame.size());
+        for ((area, _), (label, _)) in buttons.buttons.iter().zip(BUTTONS) {
+            let (fg, bg) = hud.colors.tile(1);
+            frame.render_widget(
+                Paragraph::new(format!(" {label} ")).fg(fg).bg(bg),
+                *area,
+            );
+        }
+        screen = Some(buttons);
+
+        if let Some(keymap) = hud.keymap {
+            frame.render_widget(
+                Paragraph::new(legend(keymap, board_width)).fg(Color::DarkGray),
+                Rect {
+                    x,
+                    y: footer + 3,
+                    width: board_width,
+                    height: 1,
+                },
+            );
+        }
+
+        frame.render_widget(table, board_area);
+
+        if hud.status == GameStatus::Won {
+            let area = centered(board_area, VICTORY_WIDTH, VICTORY_HEIGHT);
+            frame.render_widget(Clear, area);
+            frame.render_widget(victory_popup(board, keymap), area);
+        }
+
+        if let Some(question) = hud.question {
+            let width = question.chars().count() as u16 + 4;
+            let area = centered(board_area, width, 3);
+            frame.render_widget(Clear, area);
+            frame.render_widget(question_popup(question), area);
+        }
+
+        if let Some(stats) = hud.stats {
+            let area = centered(frame.size(), STATS_WIDTH, STATS_HEIGHT);
+            frame.render_widget(Clear, area);
+            frame.render_widget(stats_screen(stats, hud.colors, keymap), area);
+        }
+
+        if hud.help {
+            let area = centered(frame.size(), HELP_WIDTH, Action::ALL.len() as u16 + 2);
+            frame.render_widget(Clear, area);
+            frame.render_widget(help_screen(keymap), area);
+        }
+
+        if let Some(browser) = hud.dialog {
+            let area = centered(frame.size(), BROWSER_WIDTH, BROWSER_HEIGHT);
+            render_slot_browser(frame, area, browser, hud.colors);
+        }
     })?;
-    Ok(())
+    Ok(screen)
+}
+
+fn game_info(hud: &Hud<'_>) -> String {
+    let mut info = match hud.undos_left {
+        Some(count) => format!("Undo: {count} left"),
+        None => "Undo: unlimited".to_owned(),
+    };
+    if let Some(hint) = hud.hint {
+        info += &format!("  Hint: {hint}");
+    }
+    if hud.autoplay {
+        info += "  [auto]";
+    }
+    info
+}
+
+/// `key: label` for each bound action of `actions`.
+fn key_hints(keymap: &Keymap, actions: &[(Action, &str)]) -> Vec<String> {
+    actions
+        .iter()
+        .filter_map(|&(action, label)| {
+            let keys = keymap.key_names(action)?;
+            Some(format!("{keys}: {label}"))
+        })
+        .collect()
+}
+
+/// As many of the most useful bindings as fit in `width`.
+pub(crate) fn legend(keymap: &Keymap, width: u16) -> String {
+    let mut legend = String::new();
+    for hint in key_hints(keymap, &LEGEND) {
+        let len = legend.chars().count() + hint.chars().count();
+        if len + 2 > width as usize {
+            break;
+        }
+        if !legend.is_empty() {
+            legend += "  ";
+        }
+        legend += &hint;
+    }
+    legend
+}
+
+fn help_screen(keymap: &Keymap) -> Paragraph<'static> {
+    let lines = Action::ALL
+        .into_iter()
+        .map(|action| {
+            let keys = keymap
+                .key_names(action)
+                .unwrap_or_else(|| "unbound".to_owned());
+            Line::from(format!(" {:<24}{keys}", action.description()))
+        })
+        .collect::<Vec<_>>();
+    let close = key_hints(keymap, &[(Action::Help, "close")]).join("");
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Keys ")
+            .title(Title::from(format!(" {close} ")).alignment(Alignment::Right))
+            .style(Style::new().bg(Color::Black).fg(Color::White)),
+    )
+}
+
+fn victory_popup(board: &Board, keymap: &Keymap) -> Paragraph<'static> {
+    let tile = 2_u64.saturating_pow(board.target().get() as _);
+    let mut lines = vec![
+        Line::styled(format!("You reached {tile}!"), Modifier::BOLD),
+        Line::default(),
+    ];
+    lines.extend(
+        key_hints(
+            keymap,
+            &[
+                (Action::KeepGoing, "keep going"),
+                (Action::Restart, "new game"),
+            ],
+        )
+        .into_iter()
+        .map(Line::from),
+    );
+    Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::new().bg(Color::Black).fg(Color::Yellow)),
+    )
+}
+
+fn question_popup(question: &str) -> Paragraph<'_> {
+    Paragraph::new(question).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::new().bg(Color::Black).fg(Color::Yellow)),
+    )
+}
+
+/// A `width` x `height` rectangle centered in `area`, clipped to fit.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn render_slot_browser(frame: &mut Frame, area: Rect, browser: &SlotBrowser, colors: Colors) {
+    let title = match browser.purpose {
+        Purpose::Save => " Save game ",
+        Purpose::Load => " Load game ",
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .style(Style::new().bg(Color::Black).fg(Color::White));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let [body, message, help] = *Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .split(inner) else {
+        unreachable!()
+    };
+    let [list, preview] =
+        *Layout::horizontal([Constraint::Length(SLOT_LIST_WIDTH), Constraint::Min(0)]).split(body)
+    else {
+        unreachable!()
+    };
+
+    let lines = browser
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(index, slot)| {
+            let text = format!("{index} {}", slot_summary(slot));
+            if index == browser.selected as usize {
+                Line::styled(text, Modifier::REVERSED)
+            } else {
+                Line::from(text)
+            }
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(Paragraph::new(lines), list);
+    frame.render_widget(slot_preview(browser.selected_slot(), colors), preview);
+
+    if let Some(text) = &browser.message {
+        frame.render_widget(Paragraph::new(text.as_str()).fg(Color::Yellow), message);
+    }
+    let slot = browser.selected;
+    let prompt = match &browser.prompt {
+        Prompt::Browse => format!(
+            "↑↓/0-9 select  enter {}  d delete  esc cancel",
+            match browser.purpose {
+                Purpose::Save => "save",
+                Purpose::Load => "load",
+            }
+        ),
+        Prompt::ConfirmOverwrite => format!("Overwrite slot {slot}? (y/n)"),
+        Prompt::ConfirmDelete => format!("Delete slot {slot}? (y/n)"),
+        Prompt::Label(label) => format!("Label: {label}_  (enter save, esc back)"),
+    };
+    frame.render_widget(Paragraph::new(prompt).fg(Color::DarkGray), help);
+}
+
+fn stats_screen(stats: &Stats, colors: Colors, keymap: &Keymap) -> Paragraph<'static> {
+    let games = stats.games.len();
+    let mut lines = vec![
+        Line::from(format!(" {:<16}{games}", "Games played")),
+        Line::from(format!(
+            " {:<16}{} ({:.1}%)",
+            "Wins",
+            stats.wins(),
+            stats.win_rate() * 100.0
+        )),
+        Line::from(format!(" {:<16}{}", "Best score", stats.best_score())),
+        Line::from(format!(" {:<16}{:.0}", "Average score", stats.mean_score())),
+        Line::from(format!(
+            " {:<16}{:.0}",
+            "Median score",
+            stats.median_score()
+        )),
+        Line::from(format!(" {:<16}{}", "Win streak", stats.current_streak())),
+        Line::from(format!(
+            " {:<16}{}",
+            "Longest streak",
+            stats.longest_streak()
+        )),
+        Line::default(),
+        Line::styled(" Best tile", Modifier::BOLD),
+    ];
+    let histogram = stats.max_tile_histogram();
+    let most = histogram.values().copied().max().unwrap_or(1);
+    // the largest tiles are the interesting ones when they do not all fit
+    let shown = histogram.len().min(STATS_TILE_ROWS);
+    for (&exponent, &count) in histogram.iter().rev().take(shown).rev() {
+        let bar = "█".repeat((count * STATS_BAR_WIDTH / most).max(1) as usize);
+        lines.push(Line::from(vec![
+            Span::raw(format!(" {:>6} {count:>5} ", tile_value(exponent))),
+            Span::raw(bar).fg(colors.tile(exponent).1),
+        ]));
+    }
+    if games == 0 {
+        lines.push(Line::styled(" No finished games yet", Color::DarkGray));
+    }
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Stats ")
+            .title(
+                Title::from(format!(
+                    " {} ",
+                    key_hints(keymap, &[(Action::Stats, "close")]).join("")
+                ))
+                .alignment(Alignment::Right),
+            )
+            .style(Style::new().bg(Color::Black).fg(Color::White)),
+    )
+}
+
+/// One line of the slot list: score, largest tile and label.
+fn slot_summary(slot: &Slot) -> String {
+    match slot {
+        Slot::Empty => "(empty)".to_owned(),
+        Slot::Corrupt(_) => "(unreadable)".to_owned(),
+        Slot::Saved(save) => {
+            let label = save.meta.label.as_deref().unwrap_or("");
+            format!(
+                "{:>8} {:>5} {label}",
+                save.board.score(),
+                short_tile(save.board.max_exponent())
+            )
+        }
+    }
+}
+
+/// The selected slot's board in miniature, followed by its metadata.
+fn slot_preview(slot: &Slot, colors: Colors) -> Paragraph<'static> {
+    let save = match slot {
+        Slot::Empty => return Paragraph::new("Empty slot").fg(Color::DarkGray),
+        Slot::Corrupt(err) => {
+            return Paragraph::new(format!("Unreadable save:\n{err}"))
+                .fg(Color::Red)
+                .wrap(Wrap { trim: true })
+        }
+        Slot::Saved(save) => save,
+    };
+
+    let mut lines = save
+        .board
+        .board
+        .iter()
+        .map(|row| {
+            Line::from(
+                row.iter()
+                    .map(|cell| match cell {
+                        Some(tile) => Span::raw(format!("{:>5}", short_tile(tile.get())))
+                            .fg(colors.tile(tile.get()).1),
+                        None => Span::raw("    ·").fg(Color::DarkGray),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    lines.push(Line::default());
+    let board = &save.board;
+    let meta = &save.meta;
+    for (name, value) in [
+        ("Score", board.score().to_string()),
+        ("Max tile", tile_value(board.max_exponent())),
+        ("Moves", meta.moves.to_string()),
+        ("Played", format_duration(meta.elapsed_secs)),
+        ("Saved", format_timestamp(meta.saved_at)),
+    ] {
+        lines.push(Line::from(format!(" {name:<9}{value}")));
+    }
+    Paragraph::new(lines)
+}
+
+/// A tile value in at most 4 characters, e.g. `16k` for 16384, or `2⁹⁹`
+/// past the last suffix.
+pub(crate) fn short_tile(exponent: u8) -> String {
+    const SUFFIXES: [char; 6] = ['k', 'M', 'G', 'T', 'P', 'E'];
+    const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    match exponent {
+        0 => String::new(),
+        1..=13 => (1u64 << exponent).to_string(),
+        14..=69 => format!(
+            "{}{}",
+            1u64 << (exponent % 10),
+            SUFFIXES[usize::from(exponent / 10) - 1]
+        ),
+        _ => std::iter::once('2')
+            .chain(
+                exponent
+                    .to_string()
+                    .bytes()
+                    .map(|digit| SUPERSCRIPTS[usize::from(digit - b'0')]),
+            )
+            .collect(),
+    }
+}
+
+/// `h:mm:ss`, or `m:ss` under an hour.
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes}:{secs:02}")
+    }
+}
+
+/// A Unix timestamp as a UTC date and time.
+fn format_timestamp(secs: u64) -> String {
+    if secs == 0 {
+        return "unknown".to_owned();
+    }
+    let [year, month, day, hour, minute, _] = utc_datetime(secs);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+}
+
+/// Where the board goes in a frame, and how large its cells are.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct BoardLayout {
+    /// The board with its border, leaving `FOOTER_HEIGHT` rows below.
+    pub board: Rect,
+    pub cell_width: u16,
+    pub cell_height: u16,
+}
+
+impl BoardLayout {
+    /// The largest layout of roughly square cells that fits in `area`,
+    /// or `None` if not even the smallest cells do.
+    pub fn fit(board: &Board, area: Rect) -> Option<Self> {
+        let (columns, rows) = (board.width() as u16, board.height() as u16);
+        let (chrome_width, chrome_height) = Self::chrome();
+        let width = area.width.checked_sub(chrome_width)? / columns;
+        let height = area.height.checked_sub(chrome_height + FOOTER_HEIGHT)? / rows;
+        if width < MIN_CELL_WIDTH || height < MIN_CELL_HEIGHT {
+            return None;
+        }
+        // terminal cells are about twice as tall as they are wide
+        let cell_height = height.min(width / 2).max(MIN_CELL_HEIGHT);
+        let cell_width = width.min(cell_height * 2).max(MIN_CELL_WIDTH);
+        let size = centered(
+            area,
+            chrome_width + cell_width * columns,
+            chrome_height + cell_height * rows + FOOTER_HEIGHT,
+        );
+        Some(Self {
+            board: Rect {
+                height: size.height - FOOTER_HEIGHT,
+                ..size
+            },
+            cell_width,
+            cell_height,
+        })
+    }
+
+    /// The smallest terminal that fits `board`.
+    pub fn min_size(board: &Board) -> (u16, u16) {
+        let (chrome_width, chrome_height) = Self::chrome();
+        (
+            chrome_width + MIN_CELL_WIDTH * board.width() as u16,
+            chrome_height + MIN_CELL_HEIGHT * board.height() as u16 + FOOTER_HEIGHT,
+        )
+    }
+
+    /// The border and padding around the cells.
+    fn chrome() -> (u16, u16) {
+        (2 + BOARD_HORIZON_PAD * 2 + 1, 2 + BOARD_VERTICAL_PAD * 2)
+    }
 }
 
 const BOARD_HORIZON_PAD: u16 = 2;
-const BOARD_WIDTH: u16 = BOARD_HORIZON_PAD * 2 + 4 * 7;
-const CELL_HEIGHT: u16 = 3;
-const BOARD_HEIGHT: u16 = 4 + CELL_HEIGHT * 4;
+const BOARD_VERTICAL_PAD: u16 = 1;
+/// Wide enough for 131072.
+const MIN_CELL_WIDTH: u16 = 6;
+const MIN_CELL_HEIGHT: u16 = 3;
+/// The info line, the "You lost!" line, the buttons and the legend
+/// below the board.
+const FOOTER_HEIGHT: u16 = 4;
+/// Clickable buttons below the board, as many as the frame is wide for.
+const BUTTONS: [(&str, Action); 4] = [
+    ("New game", Action::Restart),
+    ("Undo", Action::Undo),
+    ("Save", Action::Save),
+    ("Load", Action::Load),
+];
+/// Bindings named in the legend, most useful first.
+const LEGEND: [(Action, &str); 5] = [
+    (Action::Help, "help"),
+    (Action::Undo, "undo"),
+    (Action::Hint, "hint"),
+    (Action::Restart, "new"),
+    (Action::Quit, "quit"),
+];
+const HELP_WIDTH: u16 = 44;
+const VICTORY_WIDTH: u16 = 24;
+const VICTORY_HEIGHT: u16 = 6;
+const BROWSER_WIDTH: u16 = 78;
+const BROWSER_HEIGHT: u16 = 20;
+const SLOT_LIST_WIDTH: u16 = 34;
+const STATS_WIDTH: u16 = 40;
+const STATS_HEIGHT: u16 = 21;
+const STATS_TILE_ROWS: usize = 10;
+const STATS_BAR_WIDTH: u32 = 20;
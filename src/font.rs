@@ -0,0 +1,97 @@
+//! Block digits for drawing tile values larger than one line of text.
+
+/// A font of the ten digits, each glyph the same size.
+struct Font {
+    width: usize,
+    glyphs: [&'static [&'static str]; 10],
+}
+
+impl Font {
+    fn height(&self) -> usize {
+        self.glyphs[0].len()
+    }
+
+    /// Width of `digits` digits with a column between each.
+    fn text_width(&self, digits: usize) -> usize {
+        digits * (self.width + 1) - 1
+    }
+}
+
+const LARGE: Font = Font {
+    width: 3,
+    glyphs: [
+        &["███", "█ █", "█ █", "█ █", "███"],
+        &[" █ ", "██ ", " █ ", " █ ", "███"],
+        &["███", "  █", "███", "█  ", "███"],
+        &["███", "  █", "███", "  █", "███"],
+        &["█ █", "█ █", "███", "  █", "  █"],
+        &["███", "█  ", "███", "  █", "███"],
+        &["███", "█  ", "███", "█ █", "███"],
+        &["███", "  █", "  █", "  █", "  █"],
+        &["███", "█ █", "███", "█ █", "███"],
+        &["███", "█ █", "███", "  █", "███"],
+    ],
+};
+
+const SMALL: Font = Font {
+    width: 3,
+    glyphs: [
+        &["█▀█", "█ █", "▀▀▀"],
+        &["▀█ ", " █ ", "▀▀▀"],
+        &["▀▀█", "█▀▀", "▀▀▀"],
+        &["▀▀█", " ▀█", "▀▀▀"],
+        &["█ █", "▀▀█", "  ▀"],
+        &["█▀▀", "▀▀█", "▀▀▀"],
+        &["█▀▀", "█▀█", "▀▀▀"],
+        &["▀▀█", "  █", "  ▀"],
+        &["█▀█", "█▀█", "▀▀▀"],
+        &["█▀█", "▀▀█", "▀▀▀"],
+    ],
+};
+
+/// Fonts from the largest down.
+const FONTS: [Font; 2] = [LARGE, SMALL];
+
+/// The decimal value of the tile `2^exponent`, for any exponent.
+pub(crate) fn tile_value(exponent: u8) -> String {
+    // doubling a little-endian decimal number keeps this exact past u64
+    let mut digits = vec![1u8];
+    for _ in 0..exponent {
+        let mut carry = 0;
+        for digit in &mut digits {
+            let doubled = *digit * 2 + carry;
+            *digit = doubled % 10;
+            carry = doubled / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+    digits
+        .iter()
+        .rev()
+        .map(|digit| char::from(b'0' + digit))
+        .collect()
+}
+
+/// `value` drawn in the largest font whose glyphs fit in `width` x
+/// `height`, one string per row, or `None` if none does.
+pub(crate) fn big_digits(value: &str, width: u16, height: u16) -> Option<Vec<String>> {
+    let digits = value
+        .bytes()
+        .map(|byte| byte.is_ascii_digit().then(|| (byte - b'0') as usize))
+        .collect::<Option<Vec<_>>>()?;
+    let font = FONTS.iter().find(|font| {
+        font.text_width(digits.len()) <= width as usize && font.height() <= height as usize
+    })?;
+    let rows = (0..font.height())
+        .map(|row| {
+            digits
+                .iter()
+                .map(|&digit| font.glyphs[digit][row])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    Some(rows)
+}
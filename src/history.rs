@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context, Error, Result};
+
+/// How many moves a player may take back in one game.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum UndoBudget {
+    Limited(u32),
+    #[default]
+    Unlimited,
+}
+
+impl UndoBudget {
+    pub const HARDCORE: Self = UndoBudget::Limited(0);
+    pub const CASUAL: Self = UndoBudget::Unlimited;
+
+    /// The name stats record games played with this budget under:
+    /// `hardcore`, `casual` or `undo-<count>`.
+    pub fn mode(self) -> String {
+        match self {
+            UndoBudget::HARDCORE => "hardcore".to_owned(),
+            UndoBudget::CASUAL => "casual".to_owned(),
+            UndoBudget::Limited(count) => format!("undo-{count}"),
+        }
+    }
+}
+
+impl FromStr for UndoBudget {
+    type Err = Error;
+
+    /// Accepts a count, `hardcore` (no undos) or `casual`/`unlimited`,
+    /// or a mode name such as `undo-3`.
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "hardcore" => Self::HARDCORE,
+            "casual" | "unlimited" => Self::CASUAL,
+            count => UndoBudget::Limited(
+                count
+                    .strip_prefix("undo-")
+                    .unwrap_or(count)
+                    .parse()
+                    .with_context(|| format!("invalid undo budget: {count}"))?,
+            ),
+        })
+    }
+}
+
+impl fmt::Display for UndoBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UndoBudget::Limited(count) => write!(f, "{count}"),
+            UndoBudget::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+/// Bounded undo/redo stacks of game states.
+///
+/// A state should capture everything a move depends on, RNG included,
+/// so that redoing restores exactly what was undone and replaying an
+/// undone move spawns the same tile.
+#[derive(Clone, Debug)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    capacity: usize,
+    budget: UndoBudget,
+    used: u32,
+}
+
+impl<T> History<T> {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    pub fn new(budget: UndoBudget) -> Self {
+        Self::with_capacity(budget, Self::DEFAULT_CAPACITY)
+    }
+
+    /// Keeps at most `capacity` states to undo, forgetting the oldest.
+    pub fn with_capacity(budget: UndoBudget, capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity,
+            budget,
+            used: 0,
+        }
+    }
+
+    /// Remembers `state` as it was before a new move, dropping any redos.
+    pub fn record(&mut self, state: T) {
+        self.redo.clear();
+        if self.capacity == 0 || self.budget == UndoBudget::HARDCORE {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(state);
+    }
+
+    /// Returns the state before the last move, keeping `current` to redo.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        if !self.can_undo() {
+            return None;
+        }
+        let prev = self.undo.pop_back()?;
+        self.redo.push(current);
+        self.used += 1;
+        Some(prev)
+    }
+
+    /// Returns the last undone state, keeping `current` to undo again.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        Some(next)
+    }
+
+    /// Undos left in the budget, `None` when unlimited.
+    pub fn remaining(&self) -> Option<u32> {
+        match self.budget {
+            UndoBudget::Limited(count) => Some(count.saturating_sub(self.used)),
+            UndoBudget::Unlimited => None,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.remaining() != Some(0) && !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn budget(&self) -> UndoBudget {
+        self.budget
+    }
+
+    /// Forgets all states but keeps the budget already spent.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
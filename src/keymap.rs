@@ -0,0 +1,345 @@
+//! Which key does what during a game.
+//!
+//! A keymap starts from a `Preset` and may rebind single actions, as
+//! the `[keys]` table of the config file does. Rebinding an action
+//! replaces all of its keys; binding it to no keys unbinds it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use crossterm::event::KeyCode;
+
+use crate::Arrow;
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Move(Arrow),
+    Quit,
+    Save,
+    Load,
+    Restart,
+    /// Keep playing after reaching the target tile.
+    KeepGoing,
+    Undo,
+    Redo,
+    Hint,
+    Autoplay,
+    Stats,
+    /// Shows every binding.
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Self; 15] = [
+        Action::Move(Arrow::Up),
+        Action::Move(Arrow::Down),
+        Action::Move(Arrow::Left),
+        Action::Move(Arrow::Right),
+        Action::Quit,
+        Action::Save,
+        Action::Load,
+        Action::Restart,
+        Action::KeepGoing,
+        Action::Undo,
+        Action::Redo,
+        Action::Hint,
+        Action::Autoplay,
+        Action::Stats,
+        Action::Help,
+    ];
+
+    /// What the action does, for the help screen.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Move(Arrow::Up) => "move up",
+            Action::Move(Arrow::Down) => "move down",
+            Action::Move(Arrow::Left) => "move left",
+            Action::Move(Arrow::Right) => "move right",
+            Action::Quit => "quit",
+            Action::Save => "save to a slot",
+            Action::Load => "load a slot",
+            Action::Restart => "new game",
+            Action::KeepGoing => "keep going after a win",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Hint => "hint",
+            Action::Autoplay => "autoplay on/off",
+            Action::Stats => "stats",
+            Action::Help => "this help",
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Move(direction) => write!(f, "{direction}"),
+            Action::Quit => f.write_str("quit"),
+            Action::Save => f.write_str("save"),
+            Action::Load => f.write_str("load"),
+            Action::Restart => f.write_str("restart"),
+            Action::KeepGoing => f.write_str("keep_going"),
+            Action::Undo => f.write_str("undo"),
+            Action::Redo => f.write_str("redo"),
+            Action::Hint => f.write_str("hint"),
+            Action::Autoplay => f.write_str("autoplay"),
+            Action::Stats => f.write_str("stats"),
+            Action::Help => f.write_str("help"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.to_string() == s)
+            .with_context(|| format!("unknown action: {s}"))
+    }
+}
+
+/// A built-in set of bindings. The arrow keys move in all of them.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Preset {
+    #[default]
+    Arrows,
+    /// Moves on `w`, `a`, `s`, `d`; save on `o` and autoplay on `p`.
+    Wasd,
+    /// Moves on `h`, `j`, `k`, `l`; hint on `i` and load on `o`.
+    Vim,
+    /// Moves on `8`, `4`, `2`, `6`.
+    Numpad,
+}
+
+impl Preset {
+    pub const ALL: [Self; 4] = [Preset::Arrows, Preset::Wasd, Preset::Vim, Preset::Numpad];
+
+    /// Keys for the four moves, in up, down, left, right order.
+    fn move_keys(self) -> Option<[char; 4]> {
+        match self {
+            Preset::Arrows => None,
+            Preset::Wasd => Some(['w', 's', 'a', 'd']),
+            Preset::Vim => Some(['k', 'j', 'h', 'l']),
+            Preset::Numpad => Some(['8', '2', '4', '6']),
+        }
+    }
+
+    fn command_key(self, action: Action) -> char {
+        match (self, action) {
+            (Preset::Wasd, Action::Save) | (Preset::Vim, Action::Load) => 'o',
+            (Preset::Wasd, Action::Autoplay) => 'p',
+            (Preset::Vim, Action::Hint) => 'i',
+            (_, Action::Quit) => 'q',
+            (_, Action::Save) => 's',
+            (_, Action::Load) => 'l',
+            (_, Action::Restart) => 'r',
+            (_, Action::KeepGoing) => 'c',
+            (_, Action::Undo) => 'u',
+            (_, Action::Redo) => 'y',
+            (_, Action::Hint) => 'h',
+            (_, Action::Autoplay) => 'a',
+            (_, Action::Stats) => 't',
+            (_, Action::Help) => '?',
+            (_, Action::Move(_)) => unreachable!("moves have no command key"),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Preset::Arrows => "arrows",
+            Preset::Wasd => "wasd",
+            Preset::Vim => "vim",
+            Preset::Numpad => "numpad",
+        })
+    }
+}
+
+impl FromStr for Preset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.to_string() == s)
+            .with_context(|| {
+                format!("unknown key preset: {s} (expected arrows, wasd, vim or numpad)")
+            })
+    }
+}
+
+/// Bindings from keys to actions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Keymap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset(Preset::default())
+    }
+}
+
+impl Keymap {
+    pub fn preset(preset: Preset) -> Self {
+        let mut bindings = Arrow::iter()
+            .into_iter()
+            .map(|direction| (KeyCode::from(direction), Action::Move(direction)))
+            .collect::<Vec<_>>();
+        if let Some(keys) = preset.move_keys() {
+            for (key, direction) in keys.into_iter().zip(Arrow::iter()) {
+                bindings.push((KeyCode::Char(key), Action::Move(direction)));
+            }
+        }
+        for action in Action::ALL {
+            if !matches!(action, Action::Move(_)) {
+                bindings.push((KeyCode::Char(preset.command_key(action)), action));
+            }
+        }
+        Self { bindings }
+    }
+
+    /// Replaces every key of `action` with `keys`.
+    pub fn bind(&mut self, action: Action, keys: impl IntoIterator<Item = KeyCode>) {
+        self.bindings.retain(|&(_, bound)| bound != action);
+        self.bindings
+            .extend(keys.into_iter().map(|key| (normalize(key), action)));
+    }
+
+    /// The action of `key`, letters matching in either case.
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        let key = normalize(key);
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == action)
+            .map(|&(key, _)| key)
+    }
+
+    /// The names of the keys of `action`, such as `u/backspace`, or
+    /// `None` if it is unbound.
+    pub fn key_names(&self, action: Action) -> Option<String> {
+        let names = self.keys(action).map(key_name).collect::<Vec<_>>();
+        (!names.is_empty()).then(|| names.join("/"))
+    }
+
+    /// Checks that no key does two things and that the game can still be
+    /// played and left.
+    pub fn validate(&self) -> Result<()> {
+        for (i, &(key, action)) in self.bindings.iter().enumerate() {
+            if let Some(&(_, other)) = self.bindings[..i]
+                .iter()
+                .find(|&&(bound, other)| bound == key && other != action)
+            {
+                bail!(
+                    "key {} is bound to both {other} and {action}",
+                    key_name(key)
+                );
+            }
+        }
+        for action in Arrow::iter()
+            .map(Action::Move)
+            .into_iter()
+            .chain([Action::Quit])
+        {
+            ensure!(
+                self.keys(action).next().is_some(),
+                "no key is bound to {action}"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A preset and the actions rebound on top of it.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyConfig {
+    pub preset: Option<Preset>,
+    pub bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl KeyConfig {
+    /// The keymap this config describes, checked for conflicts.
+    pub fn keymap(&self) -> Result<Keymap> {
+        let mut keymap = Keymap::preset(self.preset.unwrap_or_default());
+        for (action, keys) in &self.bindings {
+            keymap.bind(*action, keys.iter().copied());
+        }
+        keymap.validate()?;
+        Ok(keymap)
+    }
+}
+
+impl From<Arrow> for KeyCode {
+    fn from(direction: Arrow) -> Self {
+        match direction {
+            Arrow::Up => KeyCode::Up,
+            Arrow::Down => KeyCode::Down,
+            Arrow::Left => KeyCode::Left,
+            Arrow::Right => KeyCode::Right,
+        }
+    }
+}
+
+fn normalize(key: KeyCode) -> KeyCode {
+    match key {
+        KeyCode::Char(ch) => KeyCode::Char(ch.to_ascii_lowercase()),
+        key => key,
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("tab", KeyCode::Tab),
+    ("space", KeyCode::Char(' ')),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("delete", KeyCode::Delete),
+];
+
+/// Parses a single character, a key name such as `esc`, or `f1` to `f12`.
+pub fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Ok(normalize(KeyCode::Char(ch)));
+    }
+    let lower = name.to_ascii_lowercase();
+    if let Some(&(_, key)) = NAMED_KEYS.iter().find(|(named, _)| *named == lower) {
+        return Ok(key);
+    }
+    match lower.strip_prefix('f').map(str::parse) {
+        Some(Ok(n @ 1..=12)) => Ok(KeyCode::F(n)),
+        _ => bail!("unknown key: {name}"),
+    }
+}
+
+/// The name `parse_key` reads back as `key`.
+pub fn key_name(key: KeyCode) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|&&(_, named)| named == key) {
+        return (*name).to_owned();
+    }
+    match key {
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        key => format!("{key:?}").to_ascii_lowercase(),
+    }
+}
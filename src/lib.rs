@@ -1,96 +1,370 @@
 #![allow(unused_must_use)]
 
 use std::num::NonZeroU8;
+use std::str::FromStr;
 
-use rand::rngs::ThreadRng;
+use anyhow::{bail, ensure, Result};
 use rand::seq::SliceRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
+mod animation;
+pub use animation::{AnimatedCell, Animation, AnimationFrame, Effect};
 mod arrow;
 pub use arrow::Arrow;
+mod bitboard;
+pub use bitboard::BitBoard;
+mod config;
+pub use config::Config;
 mod display;
-pub use display::print_board;
+mod font;
+pub use display::{print_board, Hud, Screen};
+mod history;
+pub use history::{History, UndoBudget};
+mod keymap;
+pub use keymap::{key_name, parse_key, Action, KeyConfig, Keymap, Preset};
+mod outcome;
+pub use outcome::{Merge, MoveOutcome, Pos, Spawn, TileMove};
+mod swipe;
+pub use swipe::{swipe, DEFAULT_SWIPE_DISTANCE};
 
+mod theme;
+pub use theme::{ColorDepth, Colors, Palette, Theme};
+
+mod replay;
+pub use replay::{
+    RecordedMove, RecordedSpawn, Recorder, Recording, REPLAY_EXTENSION, REPLAY_VERSION,
+};
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+/// The PRNG used for games that should be reproducible from a seed.
+pub type GameRng = ChaCha8Rng;
+
+pub fn seeded_rng(seed: u64) -> GameRng {
+    GameRng::seed_from_u64(seed)
+}
+
+/// Side length of a board when none is chosen.
+pub const DEFAULT_SIDE: usize = 4;
+/// Smallest supported side length of a board.
+pub const MIN_SIDE: usize = 3;
+/// Largest supported side length of a board.
+pub const MAX_SIDE: usize = 8;
+/// One in this many spawned tiles is a 4 rather than a 2.
+pub const SPAWN_FOUR_ONE_IN: u32 = 10;
+/// Exponent of the tile that wins a game by default, i.e. 2048.
+pub const DEFAULT_TARGET: u8 = 11;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum GameStatus {
+    #[default]
+    Playing,
+    /// The target tile was just reached; waiting for the player to go on.
+    Won,
+    Lost,
+    /// The target was reached and the player chose to keep playing.
+    Continuing,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
 pub struct Board {
-    board: [[Option<NonZeroU8>; 4]; 4],
+    board: Vec<Vec<Option<NonZeroU8>>>,
     score: u64,
+    target: NonZeroU8,
+    continuing: bool,
 }
 
 impl Board {
-    pub fn new(rng: &mut ThreadRng) -> Self {
-        let mut initial_board = [[None; 4]; 4];
-        let indice = (0..4)
-            .map(|i| (0..4).map(move |j| (i, j)))
-            .flatten()
-            .collect::<Vec<_>>();
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::with_size(DEFAULT_SIDE, DEFAULT_SIDE, rng).unwrap()
+    }
+
+    /// Creates a board `width` cells wide and `height` cells high,
+    /// each side within `MIN_SIDE..=MAX_SIDE`.
+    pub fn with_size<R: Rng + ?Sized>(width: usize, height: usize, rng: &mut R) -> Result<Self> {
+        ensure!(
+            Self::is_valid_size(width, height),
+            "board size {width}x{height} is out of range ({MIN_SIDE}..={MAX_SIDE})"
+        );
+        let mut initial_board = vec![vec![None; width]; height];
+        let indice = Self::indices(width, height).collect::<Vec<_>>();
         let posi = indice.choose_multiple(rng, 2);
         posi.for_each(|&(x, y)| initial_board[x][y] = NonZeroU8::new(1));
-        initial_board.into()
+        Ok(Self::from_cells(initial_board))
+    }
+
+    fn from_cells(board: Vec<Vec<Option<NonZeroU8>>>) -> Self {
+        Self {
+            board,
+            score: 0,
+            target: NonZeroU8::new(DEFAULT_TARGET).unwrap(),
+            continuing: false,
+        }
+    }
+
+    pub fn is_valid_size(width: usize, height: usize) -> bool {
+        (MIN_SIDE..=MAX_SIDE).contains(&width) && (MIN_SIDE..=MAX_SIDE).contains(&height)
+    }
+
+    /// Checks that every row has the same, supported length.
+    /// Boards decoded from untrusted bytes should pass this before use.
+    pub fn is_well_formed(&self) -> bool {
+        Self::is_valid_size(self.width(), self.height())
+            && self.board.iter().all(|row| row.len() == self.width())
+    }
+
+    pub fn width(&self) -> usize {
+        self.board.first().map_or(0, Vec::len)
+    }
+
+    pub fn height(&self) -> usize {
+        self.board.len()
+    }
+
+    pub fn play_changed<R: Rng + ?Sized>(&mut self, direction: Arrow, rng: &mut R) -> bool {
+        self.play(direction, rng).is_some()
     }
 
-    pub fn play_changed(&mut self, direction: Arrow, rng: &mut ThreadRng) -> bool {
-        let prev_state = self.clone();
-        self.merge(direction);
-        let changed = prev_state.board != self.board;
-        if changed {
-            self.gen_num(rng);
+    /// Plays a move and spawns a tile, describing what happened.
+    /// Returns `None`, leaving the board untouched, if nothing could move.
+    pub fn play<R: Rng + ?Sized>(&mut self, direction: Arrow, rng: &mut R) -> Option<MoveOutcome> {
+        self.play_spawning(direction, rng, |rng| rng.gen_ratio(1, SPAWN_FOUR_ONE_IN))
+    }
+
+    /// Like `play`, but the spawned tile is a 4 with probability
+    /// `four_chance`.
+    pub fn play_with<R: Rng + ?Sized>(
+        &mut self,
+        direction: Arrow,
+        rng: &mut R,
+        four_chance: f64,
+    ) -> Option<MoveOutcome> {
+        self.play_spawning(direction, rng, |rng| rng.gen_bool(four_chance))
+    }
+
+    fn play_spawning<R: Rng + ?Sized>(
+        &mut self,
+        direction: Arrow,
+        rng: &mut R,
+        four: impl FnOnce(&mut R) -> bool,
+    ) -> Option<MoveOutcome> {
+        if !self.can_move(direction) {
+            return None;
         }
-        changed
+        let (moves, merges, score_delta) = self.slide(direction);
+        let spawn = self.spawn_with(rng, four);
+        Some(MoveOutcome {
+            direction,
+            moves,
+            merges,
+            score_delta,
+            spawn,
+        })
+    }
+
+    pub fn gen_num<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        self.spawn(rng).is_some()
     }
 
-    pub fn gen_num(&mut self, rng: &mut ThreadRng) -> bool {
+    /// Places a 2 (90%) or a 4 (10%) on a random empty cell.
+    pub fn spawn<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<Spawn> {
+        self.spawn_with(rng, |rng| rng.gen_ratio(1, SPAWN_FOUR_ONE_IN))
+    }
+
+    fn spawn_with<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+        four: impl FnOnce(&mut R) -> bool,
+    ) -> Option<Spawn> {
         if self.is_full() {
-            return false;
+            return None;
         }
 
-        let &(x, y) = (0..4)
-            .map(|i| (0..4).map(move |j| (i, j)))
-            .flatten()
+        let &(x, y) = Self::indices(self.width(), self.height())
             .filter(|&(x, y)| self.board[x][y].is_none())
             .collect::<Vec<_>>()
             .choose(rng)
             .unwrap();
 
-        self.board[x][y] = if rng.gen_ratio(1, 10) {
+        let exponent = if four(rng) {
             NonZeroU8::new(2)
         } else {
             NonZeroU8::new(1)
-        };
+        }
+        .unwrap();
+        self.board[x][y] = Some(exponent);
 
-        true
+        Some(Spawn {
+            at: (x, y),
+            exponent,
+        })
     }
 
     fn is_full(&self) -> bool {
-        self.board
-            .iter()
-            .map(|row| row.iter())
-            .flatten()
-            .all(Option::is_some)
+        self.board.iter().flatten().all(Option::is_some)
     }
 
-    pub fn is_lost(&self, rng: &mut ThreadRng) -> bool {
+    /// Whether sliding towards `direction` would change the board,
+    /// i.e. some tile has an empty or equal neighbour on that side.
+    pub fn can_move(&self, direction: Arrow) -> bool {
+        let (dx, dy) = direction.offset();
+        Self::indices(self.width(), self.height()).any(|(x, y)| {
+            let Some(tile) = self.board[x][y] else {
+                return false;
+            };
+            let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+                return false;
+            };
+            self.board
+                .get(nx)
+                .and_then(|row| row.get(ny))
+                .is_some_and(|&next| next.is_none() || next == Some(tile))
+        })
+    }
+
+    pub fn legal_moves(&self) -> impl Iterator<Item = Arrow> + '_ {
         Arrow::iter()
             .into_iter()
-            .all(|op| !self.clone().play_changed(op, rng))
+            .filter(|&direction| self.can_move(direction))
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.legal_moves().next().is_none()
     }
 
     pub fn score(&self) -> u64 {
         self.score
     }
+
+    /// Exponent of the largest tile, 0 on an empty board.
+    pub fn max_exponent(&self) -> u8 {
+        self.board
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|tile| tile.get())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Exponent of the tile that wins the game.
+    pub fn target(&self) -> NonZeroU8 {
+        self.target
+    }
+
+    pub fn set_target(&mut self, target: NonZeroU8) {
+        self.target = target;
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.continuing {
+            if self.is_lost() {
+                GameStatus::Lost
+            } else {
+                GameStatus::Continuing
+            }
+        } else if self.max_exponent() >= self.target.get() {
+            GameStatus::Won
+        } else if self.is_lost() {
+            GameStatus::Lost
+        } else {
+            GameStatus::Playing
+        }
+    }
+
+    /// Dismisses the victory once the target is reached, so that
+    /// `status` does not report `Won` again for this game.
+    pub fn keep_going(&mut self) {
+        if self.status() == GameStatus::Won {
+            self.continuing = true;
+        }
+    }
+
+    /// Row-major `(row, column)` pairs of a `width` x `height` grid.
+    fn indices(width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+        (0..height).flat_map(move |i| (0..width).map(move |j| (i, j)))
+    }
+}
+
+impl Board {
+    /// Cells of every line along `direction`, each starting from the edge
+    /// its tiles slide towards.
+    fn lines(&self, direction: Arrow) -> Vec<Vec<Pos>> {
+        let (width, height) = (self.width(), self.height());
+        match direction {
+            Arrow::Up => (0..width)
+                .map(|y| (0..height).map(|x| (x, y)).collect())
+                .collect(),
+            Arrow::Down => (0..width)
+                .map(|y| (0..height).rev().map(|x| (x, y)).collect())
+                .collect(),
+            Arrow::Left => (0..height)
+                .map(|x| (0..width).map(|y| (x, y)).collect())
+                .collect(),
+            Arrow::Right => (0..height)
+                .map(|x| (0..width).rev().map(|y| (x, y)).collect())
+                .collect(),
+        }
+    }
+
+    /// Slides and merges every line towards `direction` without spawning,
+    /// tracking where each tile ends up.
+    fn slide(&mut self, direction: Arrow) -> (Vec<TileMove>, Vec<Merge>, u64) {
+        let (mut moves, mut merges, mut score) = (Vec::new(), Vec::new(), 0u64);
+        for line in self.lines(direction) {
+            let tiles = line
+                .iter()
+                .filter_map(|&(x, y)| self.board[x][y].take().map(|tile| ((x, y), tile)))
+                .collect::<Vec<_>>();
+
+            let mut cells = line.iter();
+            let mut tiles = tiles.into_iter().peekable();
+            while let Some((from, exponent)) = tiles.next() {
+                let to = *cells.next().unwrap();
+                moves.push(TileMove { from, to, exponent });
+                let (x, y) = to;
+                match tiles.next_if(|&(_, next)| next == exponent) {
+                    Some((other, _)) => {
+                        moves.push(TileMove {
+                            from: other,
+                            to,
+                            exponent,
+                        });
+                        let merged = exponent.checked_add(1);
+                        self.board[x][y] = merged;
+                        if let Some(merged) = merged {
+                            merges.push(Merge {
+                                at: to,
+                                exponent: merged,
+                            });
+                            score += 2_u64.pow(merged.get() as _);
+                        }
+                    }
+                    None => self.board[x][y] = Some(exponent),
+                }
+            }
+        }
+        self.score += score;
+        (moves, merges, score)
+    }
 }
 
+/// The original squash-and-scan move logic, kept as a reference
+/// for differential tests of `slide` and `BitBoard`.
+#[cfg(test)]
 impl Board {
     fn scan(
         &mut self,
         direction: Arrow,
         mut op: impl FnMut(&mut Option<NonZeroU8>, &mut Option<NonZeroU8>),
     ) {
+        let (width, height) = (self.width(), self.height());
         match direction {
-            Arrow::Up => (0..3).for_each(|x| {
-                (0..4).map(|y| (x, y)).for_each(|(x, y)| {
+            Arrow::Up => (0..height - 1).for_each(|x| {
+                (0..width).map(|y| (x, y)).for_each(|(x, y)| {
                     let (above, below) = self.board.split_at_mut(x + 1);
                     let (above, below) = (
                         &mut above.last_mut().unwrap()[y],
@@ -99,8 +373,8 @@ impl Board {
                     op(above, below);
                 })
             }),
-            Arrow::Down => (0..3).rev().for_each(|x| {
-                (0..4).map(|y| (x, y)).for_each(|(x, y)| {
+            Arrow::Down => (0..height - 1).rev().for_each(|x| {
+                (0..width).map(|y| (x, y)).for_each(|(x, y)| {
                     let (above, below) = self.board.split_at_mut(x + 1);
                     let (above, below) = (
                         &mut above.last_mut().unwrap()[y],
@@ -109,15 +383,15 @@ impl Board {
                     op(above, below);
                 })
             }),
-            Arrow::Left => (0..4).for_each(|x| {
-                (0..3).map(|y| (x, y)).for_each(|(x, y)| {
+            Arrow::Left => (0..height).for_each(|x| {
+                (0..width - 1).map(|y| (x, y)).for_each(|(x, y)| {
                     let (left, right) = self.board[x].split_at_mut(y + 1);
                     let (left, right) = (left.last_mut().unwrap(), right.first_mut().unwrap());
                     op(left, right);
                 })
             }),
-            Arrow::Right => (0..4).for_each(|x| {
-                (0..3).rev().map(|y| (x, y)).for_each(|(x, y)| {
+            Arrow::Right => (0..height).for_each(|x| {
+                (0..width - 1).rev().map(|y| (x, y)).for_each(|(x, y)| {
                     let (left, right) = self.board[x].split_at_mut(y + 1);
                     let (left, right) = (left.last_mut().unwrap(), right.first_mut().unwrap());
                     op(left, right);
@@ -129,20 +403,22 @@ impl Board {
     fn merge(&mut self, direction: Arrow) {
         self.squash(direction);
 
+        // merged tiles land on the cell nearer to the edge, leaving an empty
+        // cell behind so they cannot merge a second time in the same move
         let mut score = 0u64;
         match direction {
             Arrow::Up | Arrow::Left => self.scan(direction, |left_above, right_below| {
                 if left_above.is_some() && left_above == right_below {
-                    *right_below = left_above.unwrap().checked_add(1);
-                    *left_above = None;
-                    score += 2_u64.pow(right_below.unwrap().get() as _);
+                    *left_above = right_below.unwrap().checked_add(1);
+                    *right_below = None;
+                    score += 2_u64.pow(left_above.unwrap().get() as _);
                 }
             }),
             Arrow::Down | Arrow::Right => self.scan(direction, |left_above, right_below| {
                 if left_above.is_some() && left_above == right_below {
-                    *left_above = right_below.unwrap().checked_add(1);
-                    *right_below = None;
-                    score += 2_u64.pow(left_above.unwrap().get() as _);
+                    *right_below = left_above.unwrap().checked_add(1);
+                    *left_above = None;
+                    score += 2_u64.pow(right_below.unwrap().get() as _);
                 }
             }),
         }
@@ -166,29 +442,73 @@ impl Board {
     }
 
     fn squash(&mut self, direction: Arrow) {
-        for _ in 0..3 {
+        let side = match direction {
+            Arrow::Up | Arrow::Down => self.height(),
+            Arrow::Left | Arrow::Right => self.width(),
+        };
+        for _ in 1..side {
             self.squash_once(direction);
         }
     }
 }
 
-impl From<[[Option<NonZeroU8>; 4]; 4]> for Board {
-    fn from(value: [[Option<NonZeroU8>; 4]; 4]) -> Self {
-        Self {
-            board: value,
-            score: 0,
-        }
+impl<const W: usize, const H: usize> From<[[Option<NonZeroU8>; W]; H]> for Board {
+    fn from(value: [[Option<NonZeroU8>; W]; H]) -> Self {
+        Self::from_cells(value.map(Vec::from).into())
     }
 }
 
-impl From<[[u8; 4]; 4]> for Board {
-    fn from(value: [[u8; 4]; 4]) -> Self {
-        Board {
-            board: value.map(|row| row.map(|pow| NonZeroU8::new(pow))),
-            score: 0,
-        }
+impl<const W: usize, const H: usize> From<[[u8; W]; H]> for Board {
+    fn from(value: [[u8; W]; H]) -> Self {
+        value.map(|row| row.map(NonZeroU8::new)).into()
     }
 }
 
+impl FromStr for Board {
+    type Err = anyhow::Error;
+
+    /// Reads tile values row by row, rows separated by `/` and tiles by
+    /// commas or spaces, `0` or `.` for an empty cell: `2,0,0,2/0,4,...`.
+    fn from_str(s: &str) -> Result<Self> {
+        let board = s
+            .split('/')
+            .map(|row| {
+                row.split([',', ' '])
+                    .filter(|tile| !tile.is_empty())
+                    .map(|tile| match tile {
+                        "0" | "." => Ok(None),
+                        tile => match tile.parse::<u64>() {
+                            Ok(value) if value >= 2 && value.is_power_of_two() => {
+                                Ok(NonZeroU8::new(value.trailing_zeros() as u8))
+                            }
+                            _ => bail!("invalid tile: {tile}"),
+                        },
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let board = Self::from_cells(board);
+        ensure!(
+            board.is_well_formed(),
+            "a board needs {MIN_SIDE} to {MAX_SIDE} rows of {MIN_SIDE} to {MAX_SIDE} tiles, all the same length"
+        );
+        Ok(board)
+    }
+}
+
+mod sim;
+pub use sim::{run_batch, simulate, Batch, GameSummary, Policy, Report, MILESTONES};
+mod solver;
+
+pub use solver::{Heuristics, Solver};
+mod stats;
+pub use stats::{GameRecord, Stats, STATS_VERSION};
+
 mod savedata;
-pub use savedata::{load, save};
+pub use savedata::{
+    abandon_autosave, clear_autosave, delete_slot, read_autosave, read_best, read_slot, read_stats,
+    record_game, save_replay, write_atomic, write_autosave, write_best, write_slot, write_stats,
+    Prompt, Purpose, Slot, SlotBrowser, Step, MAX_LABEL_LEN, SLOTS,
+};
+mod saveformat;
+pub use saveformat::{decode_save, encode_save, SaveFile, SaveMeta, FORMAT_VERSION};
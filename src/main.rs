@@ -1,119 +1,688 @@
 use std::{
-    fs::create_dir_all,
-    io::{self, Stdout},
-    path::Path,
+    io::{self, Stdout, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use directories::ProjectDirs;
+use directories::{BaseDirs, ProjectDirs};
 
-use _2048_rs::{load, print_board, save, Arrow, Board};
-use anyhow::Result;
+mod cli;
+use cli::{Command, ExportFormat, Options, EXIT_USAGE};
+mod player;
+
+use _2048_rs::{
+    abandon_autosave, clear_autosave, print_board, read_autosave, read_best, read_stats,
+    record_game, run_batch, save_replay, seeded_rng, swipe, write_autosave, write_best,
+    write_stats, Action, Animation, AnimationFrame, Board, Colors, Config, GameRecord, GameRng,
+    GameStatus, Heuristics, History, Hud, Keymap, MoveOutcome, Purpose, Recorder, Recording,
+    SaveFile, SaveMeta, SlotBrowser, Solver, Stats, Step, UndoBudget, DEFAULT_SIDE,
+    DEFAULT_SWIPE_DISTANCE,
+};
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{Event, KeyCode, KeyEvent, KeyEventKind},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
+    style::Colored,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
-use rand::rngs::ThreadRng;
+use rand::Rng;
 use ratatui::prelude::*;
 
-fn main() -> Result<()> {
-    let mut terminal = setup_terminal()?;
+/// How long autoplay waits for a key before making its next move.
+const AUTOPLAY_DELAY: Duration = Duration::from_millis(150);
+/// Settings, read from the config directory.
+const CONFIG_FILE: &str = "config.toml";
 
-    // if project specified directory is not avaliable,
-    // just place saves at ./saves/saveX
-    let project_dir = ProjectDirs::from("moe", "Meowkatee", "2048-rs");
-    let data_dir = project_dir
-        .as_ref()
-        .map(|proj| proj.data_dir())
-        .unwrap_or(&Path::new("saves"));
+fn main() -> ExitCode {
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {err:#}\n\nRun `_2048-rs --help` for usage.");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match run_command(command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Play(options) => play(options),
+        Command::Bench { batch, json } => {
+            let report = run_batch(&batch)?;
+            if json {
+                print_out(&(report.to_json() + "\n"))
+            } else {
+                print_out(&report.to_string())
+            }
+        }
+        Command::Stats {
+            filter,
+            json,
+            overrides,
+        } => {
+            let config = read_config()?.merge(overrides);
+            let mut stats = read_stats(&data_dir(&config))?;
+            stats.games.retain(|game| filter.matches(game));
+            if json {
+                print_out(&(stats.to_json() + "\n"))
+            } else {
+                print_out(&stats.to_string())
+            }
+        }
+        Command::Export {
+            format,
+            output,
+            filter,
+            overrides,
+        } => {
+            let config = read_config()?.merge(overrides);
+            let mut stats = read_stats(&data_dir(&config))?;
+            stats.games.retain(|game| filter.matches(game));
+            let exported = match format {
+                ExportFormat::Csv => stats.to_csv(),
+                ExportFormat::Json => stats.to_json() + "\n",
+            };
+            match output {
+                Some(path) => Ok(std::fs::write(path, exported)?),
+                None => print_out(&exported),
+            }
+        }
+        Command::Import { path, overrides } => {
+            let config = read_config()?.merge(overrides);
+            let data_dir = data_dir(&config);
+            let csv = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let imported = Stats::from_csv(&csv)
+                .with_context(|| format!("invalid export file {}", path.display()))?;
+            let total = imported.games.len();
+            let mut stats = read_stats(&data_dir)?;
+            let added = stats.merge(imported);
+            write_stats(&data_dir, &stats)?;
+            print_out(&format!(
+                "imported {added} of {total} games ({} already recorded)\n",
+                total - added
+            ))
+        }
+        Command::Solve { board, depth } => {
+            let solver = Solver::new(depth, Heuristics::default());
+            let direction = solver
+                .best_move(&board)
+                .context("no move is left on this board")?;
+            print_out(&format!("{direction}\n"))
+        }
+        Command::Replay {
+            path,
+            overrides,
+            no_color,
+        } => {
+            let recording = Recording::read(&path)?;
+            let positions = recording.positions()?;
+            let config = without_color(read_config()?.merge(overrides), no_color);
+            let mut terminal = setup_terminal(false)?;
+            let played = player::run(&mut terminal, &positions, config.colors());
+            restore_terminal(&mut terminal)?;
+            played
+        }
+        Command::Help(usage) => print_out(usage),
+        Command::Version => print_out(&format!("_2048-rs {}\n", env!("CARGO_PKG_VERSION"))),
+    }
+}
+
+/// Writes `text` to stdout. A reader that stops early, like `head`, is
+/// not an error.
+fn print_out(text: &str) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout
+        .write_all(text.as_bytes())
+        .and_then(|()| stdout.flush())
+    {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Turns off colors if asked to, by `--no-color` or the `NO_COLOR`
+/// environment variable, marking tiles with glyphs instead.
+fn without_color(mut config: Config, no_color: bool) -> Config {
+    if no_color {
+        Colored::set_ansi_color_disabled(true);
+    }
+    if Colored::ansi_color_disabled_memoized() {
+        config.markers = Some(true);
+    }
+    config
+}
+
+fn play(options: Options) -> Result<()> {
+    // without `--seed`, pick one at random and report it on exit
+    // so the game can be reproduced later
+    let seed = match options.seed {
+        Some(seed) => seed,
+        None => rand::thread_rng().gen(),
+    };
+
+    // a bad config file should fail before the terminal is taken over
+    let config = without_color(read_config()?.merge(options.overrides), options.no_color);
+    let keymap = config.keys.keymap().context("invalid key bindings")?;
 
-    let mut rng = rand::thread_rng();
+    let mut terminal = setup_terminal(config.mouse.unwrap_or(true))?;
+    let data_dir = &data_dir(&config);
 
-    let mut current_best = std::fs::read(data_dir.join("best"))
-        .map(|b| bitcode::decode(&b).expect("invalid best score file"))
-        .unwrap_or(0);
+    let mut rng = seeded_rng(seed);
 
-    let score = loop {
-        let (score, cont) = run(&mut terminal, data_dir, &mut rng, current_best)?;
-        current_best = current_best.max(score);
-        if !cont {
-            break score;
+    let mut current_best = read_best(data_dir)?;
+
+    // a game left unfinished by the last session, killed or not
+    let mut resume = match read_autosave(data_dir) {
+        Some(save) if !save.board.is_lost() => {
+            if ask_resume(&mut terminal, &save, current_best, config.colors())? {
+                Some(save)
+            } else {
+                let session = Session::resume(save.meta);
+                let undo = config.undo.unwrap_or_default();
+                abandon_autosave(data_dir, game_record(&save.board, &session, seed, undo))?;
+                None
+            }
+        }
+        _ => None,
+    };
+
+    let end = loop {
+        let end = run(
+            &mut terminal,
+            data_dir,
+            &mut rng,
+            seed,
+            current_best,
+            &config,
+            &keymap,
+            resume.take(),
+        )?;
+        current_best = current_best.max(end.score);
+        if !end.restart {
+            break end;
         }
     };
-    create_dir_all(data_dir)?;
-    std::fs::write(data_dir.join("best"), bitcode::encode(&current_best))?;
     restore_terminal(&mut terminal)?;
-    println!("score: {score}");
+    println!("score: {}", end.score);
+    println!("seed: {seed}");
+    if let Some(replay) = end.replay {
+        println!("replay: {}", replay.display());
+    }
     Ok(())
 }
 
-fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+/// The save directory of `config`, by default the project data dir.
+fn data_dir(config: &Config) -> PathBuf {
+    if let Some(dir) = &config.save_dir {
+        // `~` is up to the shell on the command line, but not in the config file
+        let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_owned());
+        return match (dir.strip_prefix("~"), home) {
+            (Ok(rest), Some(home)) => home.join(rest),
+            _ => dir.clone(),
+        };
+    }
+    // if project specified directory is not avaliable,
+    // just place saves at ./saves/saveX
+    ProjectDirs::from("moe", "Meowkatee", "2048-rs")
+        .map_or_else(|| PathBuf::from("saves"), |proj| proj.data_dir().to_owned())
+}
+
+fn config_dir() -> PathBuf {
+    ProjectDirs::from("moe", "Meowkatee", "2048-rs").map_or_else(
+        || PathBuf::from("config"),
+        |proj| proj.config_dir().to_owned(),
+    )
+}
+
+/// The settings of `CONFIG_FILE`, all unset if there is none.
+fn read_config() -> Result<Config> {
+    let path = config_dir().join(CONFIG_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => text
+            .parse()
+            .with_context(|| format!("invalid config file {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn setup_terminal(mouse: bool) -> Result<Terminal<CrosstermBackend<Stdout>>> {
     let mut stdout = io::stdout();
     enable_raw_mode()?;
     execute!(stdout, EnterAlternateScreen)?;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     Ok(Terminal::new(CrosstermBackend::new(stdout))?)
 }
 
 fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
     Ok(terminal.show_cursor()?)
 }
 
+/// Asks whether to pick the autosaved game back up, showing it behind
+/// the question.
+fn ask_resume(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    save: &SaveFile,
+    best: u64,
+    colors: Colors,
+) -> Result<bool> {
+    let hud = Hud {
+        prev_best: best,
+        new_best: best,
+        question: Some("Resume last game? y/n"),
+        colors,
+        ..Hud::default()
+    };
+    loop {
+        print_board(&save.board, terminal, &hud)?;
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = crossterm::event::read()?
+        {
+            match code {
+                KeyCode::Char('y' | 'Y') | KeyCode::Enter => break Ok(true),
+                KeyCode::Char('n' | 'N') | KeyCode::Esc => break Ok(false),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// What save slots keep about the game in progress besides its board.
+struct Session {
+    moves: u32,
+    label: Option<String>,
+    /// Play time before `started`, carried over from a loaded save.
+    played_before: Duration,
+    started: Instant,
+}
+
+impl Session {
+    fn new() -> Self {
+        Self::resume(SaveMeta::default())
+    }
+
+    fn resume(meta: SaveMeta) -> Self {
+        Self {
+            moves: meta.moves,
+            label: meta.label,
+            played_before: Duration::from_secs(meta.elapsed_secs),
+            started: Instant::now(),
+        }
+    }
+
+    fn save(&self, board: &Board) -> SaveFile {
+        SaveFile {
+            meta: SaveMeta {
+                saved_at: 0,
+                moves: self.moves,
+                elapsed_secs: (self.played_before + self.started.elapsed()).as_secs(),
+                label: self.label.clone(),
+            },
+            board: board.clone(),
+        }
+    }
+}
+
+/// Plays the frames of `outcome`, stopping early on any input.
+fn animate(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    board: &Board,
+    hud: Hud<'_>,
+    outcome: MoveOutcome,
+    fps: u32,
+) -> Result<()> {
+    let animation = Animation::new(outcome, fps);
+    let frame_time = Duration::from_secs(1) / fps;
+    for frame in 0..animation.frames() {
+        let hud = Hud {
+            animation: Some(AnimationFrame {
+                animation: &animation,
+                frame,
+            }),
+            ..hud
+        };
+        print_board(board, terminal, &hud)?;
+        if crossterm::event::poll(frame_time)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn game_record(board: &Board, session: &Session, seed: u64, undo: UndoBudget) -> GameRecord {
+    GameRecord {
+        played_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        score: board.score(),
+        max_exponent: board.max_exponent(),
+        target: board.target().get(),
+        moves: session.moves,
+        duration_secs: session.save(board).meta.elapsed_secs,
+        mode: undo.mode(),
+        width: board.width() as u8,
+        height: board.height() as u8,
+        seed,
+    }
+}
+
+/// How a game handed control back to `play`.
+struct GameEnd {
+    score: u64,
+    /// Whether the player asked for a new game rather than to quit.
+    restart: bool,
+    /// Where the recording of the game was written, if anything was played.
+    replay: Option<PathBuf>,
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     data_dir: &Path,
-    rng: &mut ThreadRng,
+    rng: &mut GameRng,
+    seed: u64,
     prev_best: u64,
-) -> Result<(u64, bool)> {
-    let mut board = Board::new(rng);
+    config: &Config,
+    keymap: &Keymap,
+    resume: Option<SaveFile>,
+) -> Result<GameEnd> {
+    let (mut board, mut session) = match resume {
+        Some(save) => (save.board, Session::resume(save.meta)),
+        None => {
+            let width = config.width.unwrap_or(DEFAULT_SIDE);
+            let height = config.height.unwrap_or(DEFAULT_SIDE);
+            let mut board = Board::with_size(width, height, rng)?;
+            if let Some(target) = config.target {
+                board.set_target(target);
+            }
+            (board, Session::new())
+        }
+    };
     let mut new_best = prev_best;
+    let mut saved_best = prev_best;
+    let mut autosaved: Option<Board> = None;
+    let mut recorder = Recorder::new(seed, &board);
+    // the RNG is part of every snapshot so redo spawns the same tiles
+    let undo = config.undo.unwrap_or_default();
+    let mut history = History::<(Board, GameRng)>::new(undo);
+    let mut solver = Solver::default();
+    if let Some(four_chance) = config.four_chance {
+        solver.four_chance = four_chance;
+    }
+    let mut hint = None;
+    let mut autoplay = false;
+    let mut dialog: Option<SlotBrowser> = None;
+    let mut stats: Option<Stats> = None;
+    let mut help = false;
+    let colors = config.colors();
+    let swipe_distance = config.swipe_distance.unwrap_or(DEFAULT_SWIPE_DISTANCE);
+    // where the left button went down on the board
+    let mut drag = None;
+    // the last move, still to be animated
+    let mut moved = None;
 
-    loop {
+    let restart = loop {
         new_best = new_best.max(board.score());
-        print_board(board, terminal, board.is_lost(rng), prev_best, new_best)?;
-        let input = crossterm::event::read()?;
-        match input {
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('q' | 'Q'),
+        // keep the game and the best score on disk as they change,
+        // so a killed session loses nothing
+        if new_best > saved_best {
+            write_best(data_dir, new_best)?;
+            saved_best = new_best;
+        }
+        if autosaved.as_ref() != Some(&board) {
+            if board.is_lost() {
+                clear_autosave(data_dir)?;
+            } else {
+                write_autosave(data_dir, &session.save(&board))?;
+            }
+            autosaved = Some(board.clone());
+        }
+
+        let hud = Hud {
+            status: board.status(),
+            prev_best,
+            new_best,
+            undos_left: history.remaining(),
+            hint,
+            autoplay,
+            animation: None,
+            dialog: dialog.as_ref(),
+            question: None,
+            info: None,
+            stats: stats.as_ref(),
+            colors,
+            keymap: Some(keymap),
+            help,
+        };
+        // a move is played out over the frame that shows where it ends
+        if let Some(outcome) = moved.take() {
+            let fps = config.fps.unwrap_or(Animation::DEFAULT_FPS);
+            animate(terminal, &board, hud, outcome, fps)?;
+        }
+        let screen = print_board(&board, terminal, &hud)?;
+
+        // while autoplaying, the solver moves whenever no key arrives in time
+        let waiting = !autoplay || dialog.is_some() || stats.is_some() || help;
+        let input = if waiting || crossterm::event::poll(AUTOPLAY_DELAY)? {
+            Some(crossterm::event::read()?)
+        } else {
+            None
+        };
+
+        // too small to show the board: wait for a resize, or quit
+        let Some(screen) = screen else {
+            if let Some(Event::Key(KeyEvent {
+                code,
                 kind: KeyEventKind::Press,
                 ..
-            }) => break Ok((board.score(), false)),
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('s' | 'S'),
-                kind: KeyEventKind::Release,
-                ..
-            }) => {
-                terminal.clear()?;
-                save(board, data_dir)?;
-                terminal.clear()?;
-            }
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('l' | 'L'),
-                kind: KeyEventKind::Release,
+            })) = input
+            {
+                if keymap.action(code) == Some(Action::Quit) {
+                    break false;
+                }
+            }
+            continue;
+        };
+
+        // an open dialog takes every key until it closes
+        if let Some(browser) = &mut dialog {
+            if let Some(Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
                 ..
-            }) => {
-                terminal.clear()?;
-                load(&mut board, data_dir)?;
-                terminal.clear()?;
-            }
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('r' | 'R'),
-                kind: KeyEventKind::Release,
+            })) = input
+            {
+                let current = session.save(&board);
+                match browser.handle_key(code, data_dir, Some(&current)) {
+                    Ok(Step::Stay) => {}
+                    Ok(Step::Cancel | Step::Saved) => dialog = None,
+                    Ok(Step::Loaded(loaded)) => {
+                        board = loaded.board;
+                        session = Session::resume(loaded.meta);
+                        recorder = Recorder::new(seed, &board);
+                        history.clear();
+                        hint = None;
+                        dialog = None;
+                    }
+                    Err(err) => browser.message = Some(format!("{err:#}")),
+                }
+            }
+            continue;
+        }
+
+        // so do the stats and help screens, closing on any key
+        if stats.is_some() || help {
+            if let Some(Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
                 ..
-            }) => break Ok((board.score(), true)),
-            Event::Key(KeyEvent {
+            })) = input
+            {
+                stats = None;
+                help = false;
+            }
+            continue;
+        }
+
+        let action = match input {
+            None => match solver.best_move(&board) {
+                Some(direction) => Action::Move(direction),
+                None => {
+                    autoplay = false;
+                    continue;
+                }
+            },
+            Some(Event::Key(KeyEvent {
                 code,
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
-                let Ok(direction) = Arrow::try_from(code) else {
-                    continue;
-                };
-                board.play_changed(direction, rng);
-            }
+            })) => match keymap.action(code) {
+                Some(action) => action,
+                None => continue,
+            },
+            Some(Event::Mouse(MouseEvent {
+                kind, column, row, ..
+            })) => match kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    if let Some(action) = screen.button(column, row) {
+                        action
+                    } else {
+                        drag = screen.on_board(column, row).then_some((column, row));
+                        continue;
+                    }
+                }
+                // a drag across the board moves once it is let go
+                MouseEventKind::Up(MouseButton::Left) => {
+                    match drag
+                        .take()
+                        .and_then(|from| swipe(from, (column, row), swipe_distance))
+                    {
+                        Some(direction) => Action::Move(direction),
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            },
+            // the next pass redraws at the new size
+            Some(Event::Resize(..)) => continue,
             _ => continue,
+        };
+        let direction = match action {
+            Action::Move(direction) => direction,
+            Action::Quit => break false,
+            Action::Restart => break true,
+            Action::Save => {
+                dialog = Some(SlotBrowser::open(Purpose::Save, data_dir));
+                continue;
+            }
+            Action::Load => {
+                dialog = Some(SlotBrowser::open(Purpose::Load, data_dir));
+                continue;
+            }
+            Action::KeepGoing => {
+                board.keep_going();
+                continue;
+            }
+            Action::Undo => {
+                if let Some((prev, prev_rng)) = history.undo((board.clone(), rng.clone())) {
+                    board = prev;
+                    *rng = prev_rng;
+                    session.moves = session.moves.saturating_sub(1);
+                    recorder.undo();
+                    hint = None;
+                }
+                continue;
+            }
+            Action::Redo => {
+                if let Some((next, next_rng)) = history.redo((board.clone(), rng.clone())) {
+                    board = next;
+                    *rng = next_rng;
+                    session.moves += 1;
+                    recorder.redo();
+                    hint = None;
+                }
+                continue;
+            }
+            Action::Hint => {
+                hint = solver.best_move(&board);
+                continue;
+            }
+            Action::Stats => {
+                stats = Some(read_stats(data_dir)?);
+                continue;
+            }
+            Action::Autoplay => {
+                autoplay = !autoplay;
+                continue;
+            }
+            Action::Help => {
+                help = true;
+                continue;
+            }
+        };
+
+        // the victory popup has to be dismissed first
+        if board.status() == GameStatus::Won {
+            autoplay = false;
+            continue;
+        }
+        if !board.can_move(direction) {
+            continue;
         }
+        history.record((board.clone(), rng.clone()));
+        let outcome = match config.four_chance {
+            Some(four_chance) => board.play_with(direction, rng, four_chance),
+            None => board.play(direction, rng),
+        };
+        let Some(outcome) = outcome else {
+            continue;
+        };
+        session.moves += 1;
+        recorder.record(&outcome);
+        hint = None;
+        if config.animation.unwrap_or(true) {
+            moved = Some(outcome);
+        }
+    };
+
+    // a game quit before it was lost stays in the autosave to be resumed,
+    // so only count it once it is over
+    if session.moves > 0 && (restart || board.is_lost()) {
+        record_game(data_dir, game_record(&board, &session, seed, undo))?;
     }
+
+    // keep the game for review, unless nothing was played
+    let recording = recorder.recording();
+    let replay = if recording.moves.is_empty() {
+        None
+    } else {
+        Some(save_replay(data_dir, recording)?)
+    };
+    Ok(GameEnd {
+        score: board.score(),
+        restart,
+        replay,
+    })
 }
@@ -0,0 +1,49 @@
+use std::num::NonZeroU8;
+
+use crate::Arrow;
+
+/// A `(row, column)` position on the board.
+pub type Pos = (usize, usize);
+
+/// Where one tile went during a move. Tiles that stayed put have
+/// `from == to`; both tiles of a merge end on the merge's cell.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TileMove {
+    pub from: Pos,
+    pub to: Pos,
+    /// Exponent of the tile before any merge.
+    pub exponent: NonZeroU8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Merge {
+    pub at: Pos,
+    /// Exponent of the merged tile.
+    pub exponent: NonZeroU8,
+}
+
+/// A tile placed on an empty cell after a move.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Spawn {
+    pub at: Pos,
+    pub exponent: NonZeroU8,
+}
+
+/// Everything a move did to the board, in enough detail to animate or
+/// replay it without diffing boards.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MoveOutcome {
+    pub direction: Arrow,
+    pub moves: Vec<TileMove>,
+    pub merges: Vec<Merge>,
+    pub score_delta: u64,
+    /// `None` only if the board had no room left after the move.
+    pub spawn: Option<Spawn>,
+}
+
+impl MoveOutcome {
+    /// Tiles that actually changed position.
+    pub fn moved(&self) -> impl Iterator<Item = &TileMove> {
+        self.moves.iter().filter(|tile| tile.from != tile.to)
+    }
+}
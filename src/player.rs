@@ -0,0 +1,149 @@
+//! The replay player of the `_2048-rs replay` subcommand.
+
+use std::io::Stdout;
+use std::time::Duration;
+
+use _2048_rs::{print_board, Board, Colors, GameStatus, Hud};
+use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::prelude::*;
+
+/// Playback speeds in moves per second.
+const SPEEDS: [f64; 7] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0];
+const DEFAULT_SPEED: usize = 2;
+
+struct Player<'a> {
+    positions: &'a [Board],
+    /// Moves played so far, i.e. the index of the position shown.
+    index: usize,
+    playing: bool,
+    speed: usize,
+    /// Digits typed after `g`, while choosing a move to jump to.
+    jump: Option<String>,
+}
+
+impl Player<'_> {
+    fn last(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    fn step_forward(&mut self) {
+        self.index = (self.index + 1).min(self.last());
+        if self.index == self.last() {
+            self.playing = false;
+        }
+    }
+
+    fn caption(&self) -> String {
+        if let Some(digits) = &self.jump {
+            return format!("Jump to move: {digits}_");
+        }
+        let state = if self.playing { "playing" } else { "paused" };
+        format!(
+            "Move {}/{}  {state}  {}/s",
+            self.index,
+            self.last(),
+            SPEEDS[self.speed]
+        )
+    }
+
+    /// Handles a key press, returning `false` once the player should close.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(digits) = &mut self.jump {
+            match code {
+                KeyCode::Char(digit @ '0'..='9') => digits.push(digit),
+                KeyCode::Backspace => {
+                    digits.pop();
+                }
+                KeyCode::Enter => {
+                    if let Ok(index) = digits.parse::<usize>() {
+                        self.index = index.min(self.last());
+                    }
+                    self.jump = None;
+                }
+                KeyCode::Esc => self.jump = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q' | 'Q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => {
+                // playing from the end starts over
+                if !self.playing && self.index == self.last() {
+                    self.index = 0;
+                }
+                self.playing = !self.playing;
+            }
+            KeyCode::Right | KeyCode::Char('.') => {
+                self.playing = false;
+                self.step_forward();
+            }
+            KeyCode::Left | KeyCode::Char(',') => {
+                self.playing = false;
+                self.index = self.index.saturating_sub(1);
+            }
+            KeyCode::Char('+' | '=') => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            KeyCode::Char('-') => self.speed = self.speed.saturating_sub(1),
+            KeyCode::Home => self.index = 0,
+            KeyCode::End => self.index = self.last(),
+            KeyCode::Char('g' | 'G') => {
+                self.playing = false;
+                self.jump = Some(String::new());
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+/// Plays back `positions`, the boards of a recorded game in order.
+pub fn run(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    positions: &[Board],
+    colors: Colors,
+) -> Result<()> {
+    let mut player = Player {
+        positions,
+        index: 0,
+        playing: false,
+        speed: DEFAULT_SPEED,
+        jump: None,
+    };
+    let final_score = positions.last().map_or(0, Board::score);
+
+    loop {
+        let board = &positions[player.index];
+        let caption = player.caption();
+        let hud = Hud {
+            // the victory popup would hide the rest of the game
+            status: match board.status() {
+                GameStatus::Won => GameStatus::Continuing,
+                status => status,
+            },
+            prev_best: final_score,
+            new_best: final_score,
+            info: Some(&caption),
+            colors,
+            ..Hud::default()
+        };
+        print_board(board, terminal, &hud)?;
+
+        let delay = Duration::from_secs_f64(1.0 / SPEEDS[player.speed]);
+        if player.playing && !crossterm::event::poll(delay)? {
+            player.step_forward();
+            continue;
+        }
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = crossterm::event::read()?
+        {
+            if !player.handle_key(code) {
+                break Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Recordings of whole games, for reviewing and sharing them.
+//!
+//! A `.2048replay` file is `MAGIC`, the format version as a little-endian
+//! `u16`, then the bitcode `Recording`, like save slots. Recordings also
+//! have a text form, one move per line:
+//!
+//! ```text
+//! 2048-rs replay v1
+//! seed 42
+//! target 2048
+//! continuing false
+//! score 0
+//! board
+//! 2 . . .
+//! . . . .
+//! . . 2 .
+//! . . . .
+//! moves
+//! left 3,1=2
+//! up 0,3=4
+//! ```
+//!
+//! A spawn is written as `row,column=tile`; a move that spawned nothing
+//! is just its direction.
+
+use std::fmt::Write;
+use std::num::NonZeroU8;
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::font::tile_value;
+use crate::{Arrow, Board, MoveOutcome, Spawn};
+
+/// Extension of binary replay files.
+pub const REPLAY_EXTENSION: &str = "2048replay";
+const MAGIC: [u8; 6] = *b"2048rp";
+/// Version written by `Recording::encode`.
+pub const REPLAY_VERSION: u16 = 1;
+const TEXT_HEADER: &str = "2048-rs replay v1";
+
+/// A spawned tile, with coordinates narrow enough to encode.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+pub struct RecordedSpawn {
+    pub row: u8,
+    pub column: u8,
+    pub exponent: NonZeroU8,
+}
+
+impl From<Spawn> for RecordedSpawn {
+    fn from(spawn: Spawn) -> Self {
+        let (row, column) = spawn.at;
+        Self {
+            row: row as u8,
+            column: column as u8,
+            exponent: spawn.exponent,
+        }
+    }
+}
+
+/// One turn: the move played and the tile that appeared after it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+pub struct RecordedMove {
+    pub direction: Arrow,
+    pub spawn: Option<RecordedSpawn>,
+}
+
+/// Everything needed to play a game back without its RNG.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+pub struct Recording {
+    /// Seed of the session the game was played in.
+    pub seed: u64,
+    pub initial: Board,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, initial: Board) -> Self {
+        Self {
+            seed,
+            initial,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, outcome: &MoveOutcome) {
+        self.moves.push(RecordedMove {
+            direction: outcome.direction,
+            spawn: outcome.spawn.map(Into::into),
+        });
+    }
+
+    /// Every position of the game, starting with the initial board.
+    /// Fails on a move that could not have been played.
+    pub fn positions(&self) -> Result<Vec<Board>> {
+        ensure!(
+            self.initial.is_well_formed(),
+            "replay starts from a malformed board"
+        );
+        let mut board = self.initial.clone();
+        let mut positions = vec![board.clone()];
+        for (turn, step) in (1..).zip(&self.moves) {
+            ensure!(
+                board.can_move(step.direction),
+                "move {turn}: {} is not possible",
+                step.direction
+            );
+            board.slide(step.direction);
+            if let Some(spawn) = step.spawn {
+                let (row, column) = (spawn.row as usize, spawn.column as usize);
+                let cell = board.board.get_mut(row).and_then(|r| r.get_mut(column));
+                let Some(cell @ None) = cell else {
+                    bail!("move {turn}: cannot spawn a tile at {row},{column}");
+                };
+                *cell = Some(spawn.exponent);
+            }
+            positions.push(board.clone());
+        }
+        Ok(positions)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&bitcode::encode(self));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let rest = bytes.strip_prefix(&MAGIC).context("not a 2048-rs replay")?;
+        ensure!(rest.len() >= 2, "replay is truncated");
+        let (version, payload) = rest.split_at(2);
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        ensure!(
+            version == REPLAY_VERSION,
+            "replay format v{version} is not supported (v{REPLAY_VERSION})"
+        );
+        bitcode::decode(payload).context("corrupted replay")
+    }
+
+    /// Reads a replay file in either form.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let recording = if bytes.starts_with(&MAGIC) {
+            Self::decode(&bytes)
+        } else {
+            String::from_utf8(bytes)
+                .context("not a 2048-rs replay")
+                .and_then(|text| Self::from_text(&text))
+        };
+        recording.with_context(|| format!("invalid replay {}", path.display()))
+    }
+
+    pub fn to_text(&self) -> String {
+        let board = &self.initial;
+        let mut text = format!(
+            "{TEXT_HEADER}\nseed {}\ntarget {}\ncontinuing {}\nscore {}\nboard\n",
+            self.seed,
+            tile_value(board.target.get()),
+            board.continuing,
+            board.score,
+        );
+        for row in &board.board {
+            let cells = row
+                .iter()
+                .map(|cell| cell.map_or(".".to_owned(), |tile| tile_value(tile.get())))
+                .collect::<Vec<_>>();
+            writeln!(text, "{}", cells.join(" ")).unwrap();
+        }
+        text += "moves\n";
+        for step in &self.moves {
+            text += &step.direction.to_string();
+            if let Some(spawn) = step.spawn {
+                let tile = tile_value(spawn.exponent.get());
+                write!(text, " {},{}={tile}", spawn.row, spawn.column).unwrap();
+            }
+            text += "\n";
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self> {
+        let mut lines = (1..)
+            .zip(text.lines())
+            .filter(|(_, line)| !line.trim().is_empty());
+        let mut next = |expected: &str| {
+            lines
+                .next()
+                .map(|(number, line)| (number, line.trim()))
+                .with_context(|| format!("replay ends before {expected}"))
+        };
+
+        let (number, header) = next("the header")?;
+        ensure!(
+            header == TEXT_HEADER,
+            "line {number}: expected `{TEXT_HEADER}`"
+        );
+        let seed = field(next("the seed")?, "seed")?
+            .parse()
+            .context("invalid seed")?;
+        let target = parse_tile(field(next("the target")?, "target")?)?;
+        let continuing = field(next("continuing")?, "continuing")?
+            .parse()
+            .context("invalid continuing flag")?;
+        let score = field(next("the score")?, "score")?
+            .parse()
+            .context("invalid score")?;
+        let (number, line) = next("the board")?;
+        ensure!(line == "board", "line {number}: expected `board`");
+
+        let mut cells = Vec::new();
+        let moves_line = loop {
+            let (number, line) = next("the moves")?;
+            if line == "moves" {
+                break number;
+            }
+            let row = line
+                .split_whitespace()
+                .map(|cell| match cell {
+                    "." => Ok(None),
+                    tile => parse_tile(tile).map(Some),
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("line {number}: invalid board row"))?;
+            cells.push(row);
+        };
+        let initial = Board {
+            board: cells,
+            score,
+            target,
+            continuing,
+        };
+        ensure!(
+            initial.is_well_formed(),
+            "line {moves_line}: the board is not a supported rectangle"
+        );
+
+        let mut moves = Vec::new();
+        for (number, line) in lines {
+            let step = parse_move(line.trim()).with_context(|| format!("line {number}"))?;
+            moves.push(step);
+        }
+        Ok(Self {
+            seed,
+            initial,
+            moves,
+        })
+    }
+}
+
+/// The value of a `name value` line.
+fn field<'a>((number, line): (usize, &'a str), name: &str) -> Result<&'a str> {
+    match line.split_once(' ') {
+        Some((key, value)) if key == name => Ok(value.trim()),
+        _ => bail!("line {number}: expected `{name} <value>`"),
+    }
+}
+
+/// Parses a tile value such as `2048` into its exponent.
+fn parse_tile(tile: &str) -> Result<NonZeroU8> {
+    match tile.parse::<u64>() {
+        Ok(value) if value.is_power_of_two() && value >= 2 => {
+            Ok(NonZeroU8::new(value.trailing_zeros() as u8).unwrap())
+        }
+        _ => bail!("invalid tile: {tile}"),
+    }
+}
+
+fn parse_move(line: &str) -> Result<RecordedMove> {
+    let (direction, spawn) = match line.split_once(' ') {
+        Some((direction, spawn)) => (direction, Some(spawn.trim())),
+        None => (line, None),
+    };
+    let spawn = spawn
+        .map(|spawn| {
+            let invalid = || format!("invalid spawn: {spawn} (expected row,column=tile)");
+            let (at, tile) = spawn.split_once('=').with_context(invalid)?;
+            let (row, column) = at.split_once(',').with_context(invalid)?;
+            Ok::<_, anyhow::Error>(RecordedSpawn {
+                row: row.parse().with_context(invalid)?,
+                column: column.parse().with_context(invalid)?,
+                exponent: parse_tile(tile)?,
+            })
+        })
+        .transpose()?;
+    Ok(RecordedMove {
+        direction: direction.parse()?,
+        spawn,
+    })
+}
+
+/// Records a game as it is played, following undo and redo.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Recorder {
+    recording: Recording,
+    undone: Vec<RecordedMove>,
+}
+
+impl Recorder {
+    pub fn new(seed: u64, initial: &Board) -> Self {
+        Self {
+            recording: Recording::new(seed, initial.clone()),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, outcome: &MoveOutcome) {
+        self.recording.record(outcome);
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(step) = self.recording.moves.pop() {
+            self.undone.push(step);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(step) = self.undone.pop() {
+            self.recording.moves.push(step);
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+}
@@ -1,71 +1,307 @@
-use super::Board;
-use anyhow::Result;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
-use std::{fs::create_dir_all, path::Path, io::{Write, Read}};
-
-pub fn save(board: Board, data_dir: &Path) -> Result<()> {
-    println!("\n\nPlease choose slot (0-9):");
-    loop {
-        if let Event::Key(KeyEvent {
-            code: KeyCode::Char(slot @ '0'..='9'),
-            kind: KeyEventKind::Release,
-            ..
-        }) = crossterm::event::read()?
-        {
-            let savefile_path = data_dir.join(format!("save{slot}"));
-            if savefile_path.exists() {
-                println!("Slot{slot} exists. press it's number to overwrite,");
-                println!("or any other to cancel.");
-                if let Event::Key(KeyEvent {
-                    code: KeyCode::Char(ch),
-                    kind: KeyEventKind::Press,
-                    ..
-                }) = crossterm::event::read()?
-                {
-                    if ch != slot {
-                        return Ok(());
-                    }
-                }
-            }
+use crate::saveformat::{decode_save, encode_save, SaveFile, SaveMeta};
+use crate::{GameRecord, Recording, Stats, REPLAY_EXTENSION};
+use anyhow::{Context, Result};
+use crossterm::event::KeyCode;
+use std::{
+    fs::{create_dir_all, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-            create_dir_all(data_dir)?;
-            let mut savefile = std::fs::OpenOptions::new()
-                .read(false)
-                .write(true)
-                .append(false)
-                .create(true)
-                .open(savefile_path)?;
+/// Number of save slots, `save0` to `save9`.
+pub const SLOTS: u8 = 10;
+/// Longest label a slot can be given.
+pub const MAX_LABEL_LEN: usize = 24;
+/// The game in progress, rewritten as it is played.
+const AUTOSAVE_FILE: &str = "autosave";
+const BEST_FILE: &str = "best";
+const REPLAY_DIR: &str = "replays";
+const STATS_FILE: &str = "stats";
 
-            savefile.write_all(&bitcode::encode(&board))?;
-            break Ok(());
-        }
+/// What a save slot holds, as far as the browser can tell.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Slot {
+    Empty,
+    Saved(SaveFile),
+    /// The file exists but does not decode; it can still be deleted.
+    Corrupt(String),
+}
+
+fn slot_path(data_dir: &Path, index: u8) -> PathBuf {
+    data_dir.join(format!("save{index}"))
+}
+
+pub fn read_slot(data_dir: &Path, index: u8) -> Slot {
+    let path = slot_path(data_dir, index);
+    if !path.exists() {
+        return Slot::Empty;
+    }
+    match std::fs::read(path)
+        .map_err(Into::into)
+        .and_then(|bytes| decode_save(&bytes))
+    {
+        Ok(save) => Slot::Saved(save),
+        Err(err) => Slot::Corrupt(format!("{err:#}")),
     }
 }
 
-pub fn load(board: &mut Board, data_dir: &Path) -> Result<()> {
-    println!("\n\nPlease choose slot (0-9):");
-    loop {
-        if let Event::Key(KeyEvent {
-            code: KeyCode::Char(slot @ '0'..='9'),
-            kind: KeyEventKind::Press,
-            ..
-        }) = crossterm::event::read()?
-        {
-            let savefile_path = data_dir.join(format!("save{slot}"));
-            if !savefile_path.exists() {
-                return Ok(());
-            }
+/// Writes `save` to slot `index`, stamped with the current time.
+pub fn write_slot(data_dir: &Path, index: u8, save: &SaveFile) -> Result<()> {
+    let saved_at = unix_time();
+    let save = SaveFile {
+        meta: SaveMeta {
+            saved_at,
+            ..save.meta.clone()
+        },
+        board: save.board.clone(),
+    };
+    write_atomic(&slot_path(data_dir, index), &encode_save(&save))
+        .with_context(|| format!("failed to write slot{index}"))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+pub fn delete_slot(data_dir: &Path, index: u8) -> Result<()> {
+    std::fs::remove_file(slot_path(data_dir, index))
+        .with_context(|| format!("failed to delete slot{index}"))
+}
+
+/// Replaces the file at `path` with `bytes` by writing a temporary file
+/// next to it and renaming it over, so a crash never leaves it half written.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut tmp = File::create(&tmp_path)?;
+    tmp.write_all(bytes)?;
+    tmp.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// The autosaved game, if there is a readable one.
+pub fn read_autosave(data_dir: &Path) -> Option<SaveFile> {
+    let bytes = std::fs::read(data_dir.join(AUTOSAVE_FILE)).ok()?;
+    decode_save(&bytes).ok()
+}
+
+pub fn write_autosave(data_dir: &Path, save: &SaveFile) -> Result<()> {
+    write_atomic(&data_dir.join(AUTOSAVE_FILE), &encode_save(save)).context("failed to autosave")
+}
+
+/// Forgets the autosaved game, e.g. once it is lost.
+pub fn clear_autosave(data_dir: &Path) -> Result<()> {
+    match std::fs::remove_file(data_dir.join(AUTOSAVE_FILE)) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Ends the autosaved game the player chose not to resume, counting it in
+/// the stats like any game given up for a new one.
+pub fn abandon_autosave(data_dir: &Path, game: GameRecord) -> Result<()> {
+    if game.moves > 0 {
+        record_game(data_dir, game)?;
+    }
+    clear_autosave(data_dir)
+}
+
+/// The best score so far, 0 before the first game.
+pub fn read_best(data_dir: &Path) -> Result<u64> {
+    match std::fs::read(data_dir.join(BEST_FILE)) {
+        Ok(bytes) => bitcode::decode(&bytes).context("invalid best score file"),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn write_best(data_dir: &Path, best: u64) -> Result<()> {
+    write_atomic(&data_dir.join(BEST_FILE), &bitcode::encode(&best))
+}
+
+/// Every finished game so far, none before the first one.
+pub fn read_stats(data_dir: &Path) -> Result<Stats> {
+    match std::fs::read(data_dir.join(STATS_FILE)) {
+        Ok(bytes) => Stats::decode(&bytes),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Stats::default()),
+        Err(err) => Err(err.into()),
+    }
+}
 
-            let mut savefile = std::fs::OpenOptions::new()
-                .read(true)
-                .write(false)
-                .append(false)
-                .open(savefile_path)?;
+/// Adds a finished game to the stats file.
+pub fn record_game(data_dir: &Path, game: GameRecord) -> Result<()> {
+    let mut stats = read_stats(data_dir)?;
+    stats.games.push(game);
+    write_stats(data_dir, &stats)
+}
+
+pub fn write_stats(data_dir: &Path, stats: &Stats) -> Result<()> {
+    write_atomic(&data_dir.join(STATS_FILE), &stats.encode()).context("failed to update stats")
+}
+
+/// Writes `recording` to the replay directory, both as a `.2048replay`
+/// file and in text form next to it, returning the path of the former.
+pub fn save_replay(data_dir: &Path, recording: &Recording) -> Result<PathBuf> {
+    let dir = data_dir.join(REPLAY_DIR);
+    let stamp = unix_time();
+    let path = (0..)
+        .map(|n| match n {
+            0 => dir.join(format!("{stamp}.{REPLAY_EXTENSION}")),
+            n => dir.join(format!("{stamp}-{n}.{REPLAY_EXTENSION}")),
+        })
+        .find(|path| !path.exists())
+        .unwrap();
+    write_atomic(&path, &recording.encode())?;
+    write_atomic(&path.with_extension("txt"), recording.to_text().as_bytes())?;
+    Ok(path)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Purpose {
+    Save,
+    Load,
+}
+
+/// What the browser is waiting for.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Prompt {
+    /// Picking a slot.
+    Browse,
+    ConfirmOverwrite,
+    ConfirmDelete,
+    /// Typing the label of the game about to be saved.
+    Label(String),
+}
+
+/// Where the browser stands after a key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Step {
+    Stay,
+    Cancel,
+    Saved,
+    Loaded(Box<SaveFile>),
+}
+
+/// The save and load dialog: every slot listed with a preview of the
+/// selected one. It only reacts to the keys it is given, so the game loop
+/// stays in charge of input and drawing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SlotBrowser {
+    pub purpose: Purpose,
+    pub slots: Vec<Slot>,
+    pub selected: u8,
+    pub prompt: Prompt,
+    /// Feedback on the last action, e.g. why a slot cannot be loaded.
+    pub message: Option<String>,
+}
+
+impl SlotBrowser {
+    pub fn open(purpose: Purpose, data_dir: &Path) -> Self {
+        Self {
+            purpose,
+            slots: (0..SLOTS).map(|index| read_slot(data_dir, index)).collect(),
+            selected: 0,
+            prompt: Prompt::Browse,
+            message: None,
+        }
+    }
+
+    pub fn selected_slot(&self) -> &Slot {
+        &self.slots[self.selected as usize]
+    }
 
-            let mut save = Vec::new();
-            savefile.read_to_end(&mut save);
-            *board = bitcode::decode(&save)?;
-            break Ok(());
+    fn refresh(&mut self, data_dir: &Path) {
+        self.slots[self.selected as usize] = read_slot(data_dir, self.selected);
+    }
+
+    /// Handles a key press. `current` is the game to write when saving.
+    pub fn handle_key(
+        &mut self,
+        code: KeyCode,
+        data_dir: &Path,
+        current: Option<&SaveFile>,
+    ) -> Result<Step> {
+        let prompt = std::mem::replace(&mut self.prompt, Prompt::Browse);
+        match prompt {
+            Prompt::Browse => return Ok(self.browse(code, current)),
+            Prompt::ConfirmOverwrite => {
+                if let KeyCode::Char('y' | 'Y') | KeyCode::Enter = code {
+                    self.prompt = Self::label_prompt(current);
+                }
+            }
+            Prompt::ConfirmDelete => {
+                if let KeyCode::Char('y' | 'Y') | KeyCode::Enter = code {
+                    delete_slot(data_dir, self.selected)?;
+                    self.refresh(data_dir);
+                    self.message = Some(format!("Deleted slot {}", self.selected));
+                }
+            }
+            Prompt::Label(mut label) => match code {
+                KeyCode::Enter => {
+                    let Some(current) = current else {
+                        return Ok(Step::Cancel);
+                    };
+                    let label = label.trim();
+                    let mut save = current.clone();
+                    save.meta.label = (!label.is_empty()).then(|| label.to_owned());
+                    write_slot(data_dir, self.selected, &save)?;
+                    return Ok(Step::Saved);
+                }
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    label.pop();
+                    self.prompt = Prompt::Label(label);
+                }
+                KeyCode::Char(ch) => {
+                    if label.chars().count() < MAX_LABEL_LEN {
+                        label.push(ch);
+                    }
+                    self.prompt = Prompt::Label(label);
+                }
+                _ => self.prompt = Prompt::Label(label),
+            },
         }
+        Ok(Step::Stay)
+    }
+
+    fn browse(&mut self, code: KeyCode, current: Option<&SaveFile>) -> Step {
+        self.message = None;
+        match code {
+            KeyCode::Esc | KeyCode::Char('q' | 'Q') => return Step::Cancel,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(SLOTS - 1)
+            }
+            KeyCode::Char(digit @ '0'..='9') => self.selected = digit as u8 - b'0',
+            KeyCode::Char('d' | 'D') | KeyCode::Delete if *self.selected_slot() != Slot::Empty => {
+                self.prompt = Prompt::ConfirmDelete;
+            }
+            KeyCode::Enter => match (self.purpose, self.selected_slot()) {
+                (Purpose::Save, Slot::Empty) => self.prompt = Self::label_prompt(current),
+                (Purpose::Save, _) => self.prompt = Prompt::ConfirmOverwrite,
+                (Purpose::Load, Slot::Saved(save)) => return Step::Loaded(Box::new(save.clone())),
+                (Purpose::Load, Slot::Empty) => {
+                    self.message = Some(format!("Slot {} is empty", self.selected));
+                }
+                (Purpose::Load, Slot::Corrupt(err)) => self.message = Some(err.clone()),
+            },
+            _ => {}
+        }
+        Step::Stay
+    }
+
+    /// Starts from the label of the game being saved, if it has one.
+    fn label_prompt(current: Option<&SaveFile>) -> Prompt {
+        Prompt::Label(
+            current
+                .and_then(|save| save.meta.label.clone())
+                .unwrap_or_default(),
+        )
     }
 }
@@ -0,0 +1,168 @@
+//! The on-disk format of save slots.
+//!
+//! A save is `MAGIC`, the format version as a little-endian `u16`, then
+//! the bitcode payload of that version. Bitcode is not self-describing,
+//! so every layout a build may have written is kept here and migrated
+//! forward on load.
+//!
+//! - v2: a `SaveFile`, the board along with its `SaveMeta`. No release
+//!   wrote a v1.
+//!
+//! Saves written before the header existed are "version 0": a bare
+//! `FixedBoard`, the 4x4 board of the first release.
+//!
+//! Each version is decoded through a snapshot of the types it was written
+//! from, so later changes to `Board` or `SaveFile` cannot break old saves.
+
+use std::num::NonZeroU8;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::Board;
+
+pub const MAGIC: [u8; 6] = *b"2048rs";
+/// Version written by `encode_save`.
+pub const FORMAT_VERSION: u16 = 2;
+
+/// What a slot remembers about the game besides the board itself.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, bitcode::Encode, bitcode::Decode)]
+pub struct SaveMeta {
+    /// Seconds since the Unix epoch when the slot was written, 0 if unknown.
+    pub saved_at: u64,
+    pub moves: u32,
+    /// Seconds spent playing the game so far.
+    pub elapsed_secs: u64,
+    pub label: Option<String>,
+}
+
+/// The content of a save slot.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+pub struct SaveFile {
+    pub meta: SaveMeta,
+    pub board: Board,
+}
+
+/// Saves older than v2 carry no metadata.
+impl From<Board> for SaveFile {
+    fn from(board: Board) -> Self {
+        Self {
+            meta: SaveMeta::default(),
+            board,
+        }
+    }
+}
+
+#[cfg_attr(test, derive(bitcode::Encode))]
+#[derive(bitcode::Decode)]
+pub(crate) struct FixedBoard {
+    pub board: [[Option<NonZeroU8>; 4]; 4],
+    pub score: u64,
+}
+
+/// `Board` as v2 saves hold it.
+#[cfg_attr(test, derive(bitcode::Encode))]
+#[derive(bitcode::Decode)]
+pub(crate) struct BoardV2 {
+    pub board: Vec<Vec<Option<NonZeroU8>>>,
+    pub score: u64,
+    pub target: NonZeroU8,
+    pub continuing: bool,
+}
+
+/// `SaveMeta` as v2 saves hold it.
+#[cfg_attr(test, derive(bitcode::Encode))]
+#[derive(bitcode::Decode)]
+pub(crate) struct SaveMetaV2 {
+    pub saved_at: u64,
+    pub moves: u32,
+    pub elapsed_secs: u64,
+    pub label: Option<String>,
+}
+
+/// `SaveFile` as v2 saves hold it.
+#[cfg_attr(test, derive(bitcode::Encode))]
+#[derive(bitcode::Decode)]
+pub(crate) struct SaveFileV2 {
+    pub meta: SaveMetaV2,
+    pub board: BoardV2,
+}
+
+impl From<FixedBoard> for Board {
+    fn from(old: FixedBoard) -> Self {
+        Self {
+            score: old.score,
+            ..Board::from(old.board)
+        }
+    }
+}
+
+impl From<BoardV2> for Board {
+    fn from(old: BoardV2) -> Self {
+        Self {
+            board: old.board,
+            score: old.score,
+            target: old.target,
+            continuing: old.continuing,
+        }
+    }
+}
+
+impl From<SaveFileV2> for SaveFile {
+    fn from(old: SaveFileV2) -> Self {
+        let SaveMetaV2 {
+            saved_at,
+            moves,
+            elapsed_secs,
+            label,
+        } = old.meta;
+        Self {
+            meta: SaveMeta {
+                saved_at,
+                moves,
+                elapsed_secs,
+                label,
+            },
+            board: old.board.into(),
+        }
+    }
+}
+
+pub fn encode_save(save: &SaveFile) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&bitcode::encode(save));
+    bytes
+}
+
+/// Decodes a save of any known version, migrating it to the current layout.
+pub fn decode_save(bytes: &[u8]) -> Result<SaveFile> {
+    let save = match bytes.strip_prefix(&MAGIC) {
+        Some(rest) => {
+            ensure!(rest.len() >= 2, "save is truncated");
+            let (version, payload) = rest.split_at(2);
+            let version = u16::from_le_bytes([version[0], version[1]]);
+            decode_version(version, payload)?
+        }
+        None => decode_headerless(bytes)?.into(),
+    };
+    ensure!(save.board.is_well_formed(), "save holds a malformed board");
+    Ok(save)
+}
+
+fn decode_version(version: u16, payload: &[u8]) -> Result<SaveFile> {
+    match version {
+        2 => bitcode::decode::<SaveFileV2>(payload)
+            .map(SaveFile::from)
+            .context("corrupted save (format v2)"),
+        _ if version > FORMAT_VERSION => {
+            bail!("save format v{version} is newer than this build supports (v{FORMAT_VERSION})")
+        }
+        _ => bail!("unknown save format v{version}"),
+    }
+}
+
+fn decode_headerless(bytes: &[u8]) -> Result<Board> {
+    let board =
+        bitcode::decode::<FixedBoard>(bytes).context("not a 2048-rs save, or a corrupted one")?;
+    Ok(board.into())
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Error, Result};
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::font::tile_value;
+use crate::{seeded_rng, Arrow, Board, Solver};
+
+/// How a simulated player picks its moves.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Policy {
+    /// Any legal move, uniformly.
+    Random,
+    /// The move scoring the most points right away.
+    Greedy,
+    /// The first legal move of down, left, right, up.
+    Corner,
+    Solver(Solver),
+}
+
+impl Policy {
+    pub fn choose<R: Rng + ?Sized>(&self, board: &Board, rng: &mut R) -> Option<Arrow> {
+        match self {
+            Policy::Random => board.legal_moves().choose(rng),
+            Policy::Greedy => board.legal_moves().max_by_key(|&direction| {
+                let mut next = board.clone();
+                let (_, _, score) = next.slide(direction);
+                let empty = next.board.iter().flatten().filter(|c| c.is_none()).count();
+                (score, empty)
+            }),
+            Policy::Corner => [Arrow::Down, Arrow::Left, Arrow::Right, Arrow::Up]
+                .into_iter()
+                .find(|&direction| board.can_move(direction)),
+            Policy::Solver(solver) => solver.best_move(board),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "random" => Policy::Random,
+            "greedy" => Policy::Greedy,
+            "corner" => Policy::Corner,
+            "solver" => Policy::Solver(Solver::default()),
+            _ => bail!("unknown policy: {s} (expected random, greedy, corner or solver)"),
+        })
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Policy::Random => "random",
+            Policy::Greedy => "greedy",
+            Policy::Corner => "corner",
+            Policy::Solver(_) => "solver",
+        })
+    }
+}
+
+/// The end result of one simulated game.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GameSummary {
+    pub seed: u64,
+    pub score: u64,
+    pub max_exponent: u8,
+    pub moves: u32,
+}
+
+/// Plays a whole game with `policy` on a `width` x `height` board.
+///
+/// Spawns come from `seed` alone, so policies can be compared on the
+/// exact same sequence of games.
+pub fn simulate(policy: &Policy, seed: u64, width: usize, height: usize) -> Result<GameSummary> {
+    let mut rng = seeded_rng(seed);
+    // randomised policies draw from their own stream to leave spawns alone
+    let mut policy_rng = seeded_rng(!seed);
+    let mut board = Board::with_size(width, height, &mut rng)?;
+    let mut moves = 0;
+    while let Some(direction) = policy.choose(&board, &mut policy_rng) {
+        board.play(direction, &mut rng);
+        moves += 1;
+    }
+    Ok(GameSummary {
+        seed,
+        score: board.score(),
+        max_exponent: board.max_exponent(),
+        moves,
+    })
+}
+
+/// What to simulate in a batch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Batch {
+    pub policy: Policy,
+    pub games: u32,
+    /// Game `i` is played with seed `seed + i`.
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub threads: usize,
+}
+
+/// Runs every game of `batch`, spread over its threads.
+pub fn run_batch(batch: &Batch) -> Result<Report> {
+    let started = Instant::now();
+    let threads = batch.threads.clamp(1, batch.games.max(1) as usize);
+    let mut games = thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|worker| {
+                scope.spawn(move || {
+                    (worker as u32..batch.games)
+                        .step_by(threads)
+                        .map(|i| {
+                            let seed = batch.seed.wrapping_add(i as u64);
+                            simulate(&batch.policy, seed, batch.width, batch.height)
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("simulation thread panicked"))
+            .collect::<Result<Vec<_>>>()
+    })?
+    .concat();
+    games.sort_by_key(|game| game.seed.wrapping_sub(batch.seed));
+
+    Ok(Report {
+        batch: *batch,
+        games,
+        elapsed: started.elapsed(),
+    })
+}
+
+/// Aggregate statistics of a finished batch.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub batch: Batch,
+    pub games: Vec<GameSummary>,
+    pub elapsed: Duration,
+}
+
+/// Tiles whose reach rate is always reported: 2048, 4096 and 8192.
+pub const MILESTONES: [u8; 3] = [11, 12, 13];
+
+impl Report {
+    fn sorted_scores(&self) -> Vec<u64> {
+        let mut scores = self.games.iter().map(|game| game.score).collect::<Vec<_>>();
+        scores.sort_unstable();
+        scores
+    }
+
+    /// Score at quantile `q` in `0.0..=1.0`, nearest rank.
+    pub fn score_quantile(&self, q: f64) -> u64 {
+        let scores = self.sorted_scores();
+        if scores.is_empty() {
+            return 0;
+        }
+        let rank = (q * (scores.len() - 1) as f64).round() as usize;
+        scores[rank]
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        self.mean(|game| game.score as f64)
+    }
+
+    pub fn mean_moves(&self) -> f64 {
+        self.mean(|game| game.moves as f64)
+    }
+
+    fn mean(&self, value: impl Fn(&GameSummary) -> f64) -> f64 {
+        if self.games.is_empty() {
+            return 0.0;
+        }
+        self.games.iter().map(value).sum::<f64>() / self.games.len() as f64
+    }
+
+    /// How many games ended with each largest tile exponent.
+    pub fn max_tile_histogram(&self) -> BTreeMap<u8, u32> {
+        let mut histogram = BTreeMap::new();
+        for game in &self.games {
+            *histogram.entry(game.max_exponent).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Share of games whose largest tile reached at least `exponent`.
+    pub fn reach_rate(&self, exponent: u8) -> f64 {
+        self.mean(|game| (game.max_exponent >= exponent) as u8 as f64)
+    }
+
+    pub fn games_per_second(&self) -> f64 {
+        self.games.len() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn to_json(&self) -> String {
+        let histogram = self
+            .max_tile_histogram()
+            .iter()
+            .map(|(exponent, count)| format!("\"{}\": {count}", tile_value(*exponent)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let reach = MILESTONES
+            .iter()
+            .map(|&exponent| {
+                format!(
+                    "\"{}\": {}",
+                    tile_value(exponent),
+                    self.reach_rate(exponent)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let batch = &self.batch;
+        format!(
+            concat!(
+                "{{\"policy\": \"{}\", \"games\": {}, \"seed\": {}, \"size\": \"{}x{}\", ",
+                "\"threads\": {}, \"elapsed_secs\": {}, \"games_per_second\": {}, ",
+                "\"score\": {{\"min\": {}, \"median\": {}, \"mean\": {}, \"p90\": {}, \"max\": {}}}, ",
+                "\"mean_moves\": {}, \"max_tile\": {{{}}}, \"reach_rate\": {{{}}}}}"
+            ),
+            batch.policy,
+            self.games.len(),
+            batch.seed,
+            batch.width,
+            batch.height,
+            batch.threads,
+            self.elapsed.as_secs_f64(),
+            self.games_per_second(),
+            self.score_quantile(0.0),
+            self.score_quantile(0.5),
+            self.mean_score(),
+            self.score_quantile(0.9),
+            self.score_quantile(1.0),
+            self.mean_moves(),
+            histogram,
+            reach,
+        )
+    }
+}
+
+/// A human readable summary table.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let batch = &self.batch;
+        writeln!(
+            f,
+            "policy {} on {}x{}, {} games from seed {}",
+            batch.policy,
+            batch.width,
+            batch.height,
+            self.games.len(),
+            batch.seed
+        )?;
+        writeln!(
+            f,
+            "{:.2}s on {} threads, {:.1} games/s",
+            self.elapsed.as_secs_f64(),
+            batch.threads,
+            self.games_per_second()
+        )?;
+        writeln!(f)?;
+        writeln!(f, "score")?;
+        for (label, value) in [
+            ("min", self.score_quantile(0.0) as f64),
+            ("median", self.score_quantile(0.5) as f64),
+            ("mean", self.mean_score()),
+            ("p90", self.score_quantile(0.9) as f64),
+            ("max", self.score_quantile(1.0) as f64),
+        ] {
+            writeln!(f, "  {label:<10}{value:>12.0}")?;
+        }
+        writeln!(f, "  {:<10}{:>12.1}", "moves/game", self.mean_moves())?;
+        writeln!(f)?;
+        writeln!(f, "{:<12}{:>12}{:>10}", "max tile", "games", "share")?;
+        for (exponent, count) in self.max_tile_histogram() {
+            let share = count as f64 / self.games.len() as f64 * 100.0;
+            writeln!(f, "  {:<10}{count:>12}{share:>9.1}%", tile_value(exponent))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "reached")?;
+        for exponent in MILESTONES {
+            let rate = self.reach_rate(exponent) * 100.0;
+            writeln!(f, "  {:<10}{rate:>11.1}%", tile_value(exponent))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::num::NonZeroU8;
+
+use crate::{Arrow, Board, SPAWN_FOUR_ONE_IN};
+
+/// Weights of the features the solver scores leaf boards with.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Heuristics {
+    /// Rewards rows and columns whose tiles only grow in one direction.
+    pub monotonicity: f64,
+    /// Penalises exponent gaps between neighbouring tiles.
+    pub smoothness: f64,
+    /// Rewards each empty cell.
+    pub empty: f64,
+    /// Rewards keeping the largest tile in a corner, per exponent.
+    pub corner: f64,
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Self {
+            monotonicity: 1.0,
+            smoothness: 0.1,
+            empty: 2.7,
+            corner: 1.0,
+        }
+    }
+}
+
+/// Depth-limited expectimax: the player picks the best move, then every
+/// possible spawn is weighed by its chance of happening.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Solver {
+    /// Player moves to look ahead.
+    pub depth: u32,
+    pub heuristics: Heuristics,
+    /// Chance that a spawned tile is a 4 rather than a 2.
+    pub four_chance: f64,
+}
+
+/// Chance branches less likely than this are scored without searching.
+const PRUNE_PROBABILITY: f64 = 1e-4;
+const LOST_SCORE: f64 = -1e9;
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DEPTH, Heuristics::default())
+    }
+}
+
+impl Solver {
+    pub const DEFAULT_DEPTH: u32 = 2;
+
+    pub fn new(depth: u32, heuristics: Heuristics) -> Self {
+        Self {
+            depth,
+            heuristics,
+            four_chance: 1.0 / SPAWN_FOUR_ONE_IN as f64,
+        }
+    }
+
+    /// The move with the best expected outcome, `None` if the game is lost.
+    pub fn best_move(&self, board: &Board) -> Option<Arrow> {
+        board
+            .legal_moves()
+            .map(|direction| {
+                let mut next = board.clone();
+                next.slide(direction);
+                (direction, self.chance(&next, self.depth.max(1), 1.0))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(direction, _)| direction)
+    }
+
+    fn player(&self, board: &Board, depth: u32, probability: f64) -> f64 {
+        if depth == 0 || probability < PRUNE_PROBABILITY {
+            return self.evaluate(board);
+        }
+        board
+            .legal_moves()
+            .map(|direction| {
+                let mut next = board.clone();
+                next.slide(direction);
+                self.chance(&next, depth, probability)
+            })
+            .max_by(f64::total_cmp)
+            .unwrap_or(LOST_SCORE)
+    }
+
+    /// Averages over every empty cell receiving a 2 or a 4.
+    fn chance(&self, board: &Board, depth: u32, probability: f64) -> f64 {
+        let empty = Board::indices(board.width(), board.height())
+            .filter(|&(x, y)| board.board[x][y].is_none())
+            .collect::<Vec<_>>();
+        if empty.is_empty() {
+            return self.player(board, depth - 1, probability);
+        }
+
+        let four_chance = self.four_chance;
+        let cell_probability = probability / empty.len() as f64;
+        let mut next = board.clone();
+        let total = empty
+            .iter()
+            .map(|&(x, y)| {
+                [(1, 1.0 - four_chance), (2, four_chance)]
+                    .into_iter()
+                    .map(|(exponent, chance)| {
+                        next.board[x][y] = NonZeroU8::new(exponent);
+                        let score = self.player(&next, depth - 1, cell_probability * chance);
+                        next.board[x][y] = None;
+                        score * chance
+                    })
+                    .sum::<f64>()
+            })
+            .sum::<f64>();
+        total / empty.len() as f64
+    }
+
+    /// Heuristic value of a board, higher is better.
+    pub fn evaluate(&self, board: &Board) -> f64 {
+        let exponent = |x: usize, y: usize| board.board[x][y].map_or(0, NonZeroU8::get) as f64;
+        let (width, height) = (board.width(), board.height());
+        let rows = (0..height)
+            .map(|x| (0..width).map(|y| exponent(x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let columns = (0..width)
+            .map(|y| (0..height).map(|x| exponent(x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let lines = rows.iter().chain(&columns);
+
+        let mut monotonicity = 0.0;
+        let mut smoothness = 0.0;
+        for line in lines {
+            let (mut rising, mut falling) = (0.0, 0.0);
+            for pair in line.windows(2) {
+                let diff = pair[1] - pair[0];
+                if diff > 0.0 {
+                    rising += diff;
+                } else {
+                    falling -= diff;
+                }
+            }
+            monotonicity -= f64::min(rising, falling);
+
+            let tiles = line.iter().filter(|&&e| e > 0.0).collect::<Vec<_>>();
+            smoothness -= tiles.windows(2).map(|p| (p[0] - p[1]).abs()).sum::<f64>();
+        }
+
+        let empty = board.board.iter().flatten().filter(|c| c.is_none()).count() as f64;
+
+        let max = board.max_exponent() as f64;
+        let corners = [
+            (0, 0),
+            (0, width - 1),
+            (height - 1, 0),
+            (height - 1, width - 1),
+        ];
+        let cornered = corners.iter().any(|&(x, y)| exponent(x, y) == max);
+        let corner = if cornered { max } else { 0.0 };
+
+        let h = &self.heuristics;
+        h.monotonicity * monotonicity
+            + h.smoothness * smoothness
+            + h.empty * empty
+            + h.corner * corner
+    }
+}
@@ -0,0 +1,383 @@
+//! The history of finished games and what it adds up to.
+//!
+//! The stats file is `MAGIC`, the format version as a little-endian `u16`,
+//! then the bitcode list of `GameRecord`s, oldest first.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::font::tile_value;
+
+const MAGIC: [u8; 6] = *b"2048st";
+/// Version written by `Stats::encode`.
+pub const STATS_VERSION: u16 = 1;
+
+/// One finished game.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, bitcode::Encode, bitcode::Decode)]
+pub struct GameRecord {
+    /// Seconds since the Unix epoch when the game ended.
+    pub played_at: u64,
+    pub score: u64,
+    pub max_exponent: u8,
+    /// Exponent of the tile that would have won the game.
+    pub target: u8,
+    pub moves: u32,
+    pub duration_secs: u64,
+    /// How the game was played, e.g. `hardcore`.
+    pub mode: String,
+    pub width: u8,
+    pub height: u8,
+    pub seed: u64,
+}
+
+impl GameRecord {
+    pub fn won(&self) -> bool {
+        self.max_exponent >= self.target
+    }
+}
+
+/// Every recorded game, oldest first.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default, bitcode::Encode, bitcode::Decode)]
+pub struct Stats {
+    pub games: Vec<GameRecord>,
+}
+
+impl Stats {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&STATS_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&bitcode::encode(self));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let rest = bytes
+            .strip_prefix(&MAGIC)
+            .context("not a 2048-rs stats file")?;
+        ensure!(rest.len() >= 2, "stats file is truncated");
+        let (version, payload) = rest.split_at(2);
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        ensure!(
+            version == STATS_VERSION,
+            "stats format v{version} is not supported (v{STATS_VERSION})"
+        );
+        bitcode::decode(payload).context("corrupted stats file")
+    }
+
+    pub fn best_score(&self) -> u64 {
+        self.games.iter().map(|game| game.score).max().unwrap_or(0)
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        if self.games.is_empty() {
+            return 0.0;
+        }
+        self.games.iter().map(|game| game.score as f64).sum::<f64>() / self.games.len() as f64
+    }
+
+    /// The middle score, averaging the two middle ones of an even count.
+    pub fn median_score(&self) -> f64 {
+        let mut scores = self.games.iter().map(|game| game.score).collect::<Vec<_>>();
+        scores.sort_unstable();
+        match scores.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => scores[n / 2] as f64,
+            n => (scores[n / 2 - 1] + scores[n / 2]) as f64 / 2.0,
+        }
+    }
+
+    /// How many games ended with each largest tile exponent.
+    pub fn max_tile_histogram(&self) -> BTreeMap<u8, u32> {
+        let mut histogram = BTreeMap::new();
+        for game in &self.games {
+            *histogram.entry(game.max_exponent).or_default() += 1;
+        }
+        histogram
+    }
+
+    pub fn wins(&self) -> usize {
+        self.games.iter().filter(|game| game.won()).count()
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        self.wins() as f64 / self.games.len().max(1) as f64
+    }
+
+    /// Wins in a row up to the latest game.
+    pub fn current_streak(&self) -> usize {
+        self.games
+            .iter()
+            .rev()
+            .take_while(|game| game.won())
+            .count()
+    }
+
+    pub fn longest_streak(&self) -> usize {
+        let mut streak = 0;
+        let mut longest = 0;
+        for game in &self.games {
+            streak = if game.won() { streak + 1 } else { 0 };
+            longest = longest.max(streak);
+        }
+        longest
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "date,score,max_tile,won,moves,duration_secs,mode,width,height,seed,target\n",
+        );
+        for game in &self.games {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                iso_datetime(game.played_at),
+                game.score,
+                tile_value(game.max_exponent),
+                game.won(),
+                game.moves,
+                game.duration_secs,
+                csv_field(&game.mode),
+                game.width,
+                game.height,
+                game.seed,
+                tile_value(game.target),
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let games = self
+            .games
+            .iter()
+            .map(|game| {
+                format!(
+                    concat!(
+                        "{{\"date\": \"{}\", \"score\": {}, \"max_tile\": {}, \"won\": {}, ",
+                        "\"moves\": {}, \"duration_secs\": {}, \"mode\": \"{}\", ",
+                        "\"width\": {}, \"height\": {}, \"seed\": {}, \"target\": {}}}"
+                    ),
+                    iso_datetime(game.played_at),
+                    game.score,
+                    tile_value(game.max_exponent),
+                    game.won(),
+                    game.moves,
+                    game.duration_secs,
+                    json_escape(&game.mode),
+                    game.width,
+                    game.height,
+                    game.seed,
+                    tile_value(game.target),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n  ");
+        if games.is_empty() {
+            "[]".to_owned()
+        } else {
+            format!("[\n  {games}\n]")
+        }
+    }
+
+    /// Reads games written by `to_csv`.
+    pub fn from_csv(csv: &str) -> Result<Self> {
+        let mut rows = csv_rows(csv)?.into_iter().enumerate();
+        let (_, header) = rows.next().context("empty CSV file")?;
+        let columns = header
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect::<HashMap<_, _>>();
+        let games = rows
+            .filter(|(_, row)| !row.iter().all(String::is_empty))
+            .map(|(i, row)| {
+                game_from_row(&columns, &row).with_context(|| format!("line {}", i + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { games })
+    }
+
+    /// Adds the games of `other` not already recorded, keeping the games
+    /// in the order they were played. Returns how many were added.
+    pub fn merge(&mut self, other: Stats) -> usize {
+        let mut known = self.games.iter().cloned().collect::<HashSet<_>>();
+        let before = self.games.len();
+        self.games.extend(
+            other
+                .games
+                .into_iter()
+                .filter(|game| known.insert(game.clone())),
+        );
+        self.games.sort_by_key(|game| game.played_at);
+        self.games.len() - before
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16}{:>10}", "games played", self.games.len())?;
+        writeln!(
+            f,
+            "{:<16}{:>10} ({:.1}%)",
+            "wins",
+            self.wins(),
+            self.win_rate() * 100.0
+        )?;
+        writeln!(f, "{:<16}{:>10}", "best score", self.best_score())?;
+        writeln!(f, "{:<16}{:>10.0}", "average score", self.mean_score())?;
+        writeln!(f, "{:<16}{:>10.0}", "median score", self.median_score())?;
+        writeln!(f, "{:<16}{:>10}", "win streak", self.current_streak())?;
+        writeln!(f, "{:<16}{:>10}", "longest streak", self.longest_streak())?;
+        let histogram = self.max_tile_histogram();
+        if !histogram.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<16}{:>10}", "max tile", "games")?;
+            for (exponent, count) in histogram.into_iter().rev() {
+                writeln!(f, "  {:<14}{count:>10}", tile_value(exponent))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `[year, month, day, hour, minute, second]` in UTC of a Unix timestamp.
+pub(crate) fn utc_datetime(secs: u64) -> [u64; 6] {
+    let (hour, minute, second) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+    // days since the epoch to a civil date, after Howard Hinnant
+    let z = secs / 86400 + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    [year, month, day, hour, minute, second]
+}
+
+fn iso_datetime(secs: u64) -> String {
+    let [year, month, day, hour, minute, second] = utc_datetime(secs);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// A row of `Stats::to_csv`, its fields found through `columns`.
+fn game_from_row(columns: &HashMap<&str, usize>, row: &[String]) -> Result<GameRecord> {
+    let field = |name: &str| -> Result<&str> {
+        let column = *columns
+            .get(name)
+            .with_context(|| format!("missing column {name}"))?;
+        row.get(column)
+            .map(String::as_str)
+            .with_context(|| format!("missing {name}"))
+    };
+    let number = |name: &str| -> Result<u64> {
+        let value = field(name)?;
+        value
+            .parse()
+            .with_context(|| format!("invalid {name}: {value}"))
+    };
+    let exponent = |name: &str| -> Result<u8> {
+        match number(name)? {
+            tile if tile >= 2 && tile.is_power_of_two() => Ok(tile.trailing_zeros() as u8),
+            tile => bail!("invalid {name}: {tile}"),
+        }
+    };
+    let date = field("date")?;
+    let max_exponent = exponent("max_tile")?;
+    let target = exponent("target")?;
+    Ok(GameRecord {
+        played_at: parse_iso_datetime(date).with_context(|| format!("invalid date: {date}"))?,
+        score: number("score")?,
+        max_exponent,
+        target,
+        moves: number("moves")?.try_into().context("invalid moves")?,
+        duration_secs: number("duration_secs")?,
+        mode: field("mode")?.to_owned(),
+        width: number("width")?.try_into().context("invalid width")?,
+        height: number("height")?.try_into().context("invalid height")?,
+        seed: number("seed")?,
+    })
+}
+
+/// The Unix timestamp of a date written by `iso_datetime`.
+fn parse_iso_datetime(date: &str) -> Option<u64> {
+    let date = date.strip_suffix('Z')?;
+    let (day, time) = date.split_once('T')?;
+    let mut day = day.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (day.next()?.ok()?, day.next()?.ok()?, day.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+    // the inverse of `utc_datetime`
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, yoe) = (year / 400, year % 400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Splits CSV into rows of fields, unquoting `"…"` fields.
+fn csv_rows(csv: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = csv.chars().peekable();
+    let mut quoted = false;
+    while let Some(ch) = chars.next() {
+        match (quoted, ch) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, ch) => field.push(ch),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, '\r') => {}
+            (false, ch) => field.push(ch),
+        }
+    }
+    ensure!(!quoted, "unterminated quoted field");
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Quotes `field` if it would otherwise break the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn json_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|ch| match ch {
+            '"' => vec!['\\', '"'],
+            '\\' => vec!['\\', '\\'],
+            '\n' => vec!['\\', 'n'],
+            ch => vec![ch],
+        })
+        .collect()
+}
@@ -0,0 +1,29 @@
+//! Reading mouse and touchpad drags as moves.
+
+use crate::Arrow;
+
+/// How far a drag must go to count as a swipe, in columns. A row counts
+/// as two columns, terminal cells being about twice as tall as wide.
+pub const DEFAULT_SWIPE_DISTANCE: u16 = 4;
+
+/// The move of a drag from `from` to `to`, both `(column, row)`: its
+/// dominant direction, or `None` if it is shorter than `min_distance`
+/// or exactly diagonal.
+pub fn swipe(from: (u16, u16), to: (u16, u16), min_distance: u16) -> Option<Arrow> {
+    let dx = i32::from(to.0) - i32::from(from.0);
+    let dy = (i32::from(to.1) - i32::from(from.1)) * 2;
+    if dx.abs().max(dy.abs()) < i32::from(min_distance.max(1)) || dx.abs() == dy.abs() {
+        return None;
+    }
+    Some(if dx.abs() > dy.abs() {
+        if dx > 0 {
+            Arrow::Right
+        } else {
+            Arrow::Left
+        }
+    } else if dy > 0 {
+        Arrow::Down
+    } else {
+        Arrow::Up
+    })
+}
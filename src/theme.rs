@@ -0,0 +1,424 @@
+//! Color schemes for the board.
+//!
+//! Palettes are written in 24-bit color and brought down to what the
+//! terminal supports when drawn, see `ColorDepth`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+use ratatui::style::{Color, Modifier};
+
+type Rgb = (u8, u8, u8);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Theme {
+    /// The colors of the original game.
+    #[default]
+    Classic,
+    Dark,
+    Light,
+    Solarized,
+    HighContrast,
+    /// Safe for red-green color blindness, weak greens.
+    Deuteranopia,
+    /// Safe for red-green color blindness, weak reds.
+    Protanopia,
+    /// Safe for blue-yellow color blindness.
+    Tritanopia,
+}
+
+impl Theme {
+    pub const ALL: [Self; 8] = [
+        Theme::Classic,
+        Theme::Dark,
+        Theme::Light,
+        Theme::Solarized,
+        Theme::HighContrast,
+        Theme::Deuteranopia,
+        Theme::Protanopia,
+        Theme::Tritanopia,
+    ];
+
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Classic => &CLASSIC,
+            Theme::Dark => &DARK,
+            Theme::Light => &LIGHT,
+            Theme::Solarized => &SOLARIZED,
+            Theme::HighContrast => &HIGH_CONTRAST,
+            Theme::Deuteranopia => &DEUTERANOPIA,
+            Theme::Protanopia => &PROTANOPIA,
+            Theme::Tritanopia => &TRITANOPIA,
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Classic => "classic",
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Solarized => "solarized",
+            Theme::HighContrast => "high-contrast",
+            Theme::Deuteranopia => "deuteranopia",
+            Theme::Protanopia => "protanopia",
+            Theme::Tritanopia => "tritanopia",
+        })
+    }
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.to_string() == s)
+            .with_context(|| {
+                let names = Theme::ALL.map(|theme| theme.to_string());
+                format!("unknown theme: {s} (expected one of {})", names.join(", "))
+            })
+    }
+}
+
+/// The colors of a theme.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Palette {
+    /// Foreground and background of each tile, from 2 up; larger tiles
+    /// share the last entry.
+    pub tiles: &'static [(Rgb, Rgb)],
+    pub empty: Rgb,
+    pub background: Rgb,
+    pub border: Rgb,
+}
+
+impl Palette {
+    pub fn tile(&self, exponent: u8) -> (Rgb, Rgb) {
+        let index = (exponent.max(1) - 1) as usize;
+        self.tiles[index.min(self.tiles.len() - 1)]
+    }
+}
+
+const CLASSIC: Palette = Palette {
+    tiles: &[
+        ((119, 110, 101), (238, 228, 218)),
+        ((119, 110, 101), (237, 224, 200)),
+        ((249, 246, 242), (242, 177, 121)),
+        ((249, 246, 242), (245, 149, 99)),
+        ((249, 246, 242), (246, 124, 95)),
+        ((249, 246, 242), (246, 94, 59)),
+        ((249, 246, 242), (237, 207, 114)),
+        ((249, 246, 242), (237, 204, 97)),
+        ((249, 246, 242), (237, 200, 80)),
+        ((249, 246, 242), (237, 197, 63)),
+        ((249, 246, 242), (237, 194, 46)),
+        ((249, 246, 242), (60, 58, 50)),
+    ],
+    empty: (205, 193, 180),
+    background: (187, 173, 160),
+    border: (143, 122, 102),
+};
+
+const DARK: Palette = Palette {
+    tiles: &[
+        ((230, 230, 230), (60, 60, 70)),
+        ((230, 230, 230), (85, 80, 105)),
+        ((20, 20, 20), (255, 163, 0)),
+        ((20, 20, 20), (255, 119, 168)),
+        ((240, 240, 240), (255, 0, 77)),
+        ((240, 240, 240), (171, 82, 54)),
+        ((20, 20, 20), (255, 240, 36)),
+        ((20, 20, 20), (0, 231, 86)),
+        ((240, 240, 240), (0, 135, 81)),
+        ((20, 20, 20), (41, 173, 255)),
+        ((240, 240, 240), (131, 118, 156)),
+        ((240, 240, 240), (126, 37, 83)),
+    ],
+    empty: (38, 38, 42),
+    background: (24, 24, 28),
+    border: (95, 87, 79),
+};
+
+const LIGHT: Palette = Palette {
+    tiles: &[
+        ((60, 60, 60), (235, 235, 235)),
+        ((60, 60, 60), (215, 225, 240)),
+        ((60, 60, 60), (180, 215, 250)),
+        ((60, 60, 60), (170, 230, 200)),
+        ((60, 60, 60), (250, 225, 150)),
+        ((60, 60, 60), (250, 190, 140)),
+        ((255, 255, 255), (235, 120, 100)),
+        ((255, 255, 255), (210, 90, 150)),
+        ((255, 255, 255), (150, 100, 200)),
+        ((255, 255, 255), (80, 110, 210)),
+        ((255, 255, 255), (40, 150, 120)),
+        ((255, 255, 255), (50, 50, 60)),
+    ],
+    empty: (245, 245, 240),
+    background: (225, 222, 212),
+    border: (160, 155, 145),
+};
+
+const SOLARIZED: Palette = Palette {
+    tiles: &[
+        ((238, 232, 213), (88, 110, 117)),
+        ((238, 232, 213), (101, 123, 131)),
+        ((0, 43, 54), (181, 137, 0)),
+        ((0, 43, 54), (203, 75, 22)),
+        ((253, 246, 227), (220, 50, 47)),
+        ((253, 246, 227), (211, 54, 130)),
+        ((253, 246, 227), (108, 113, 196)),
+        ((253, 246, 227), (38, 139, 210)),
+        ((0, 43, 54), (42, 161, 152)),
+        ((0, 43, 54), (133, 153, 0)),
+        ((0, 43, 54), (238, 232, 213)),
+        ((0, 43, 54), (253, 246, 227)),
+    ],
+    empty: (7, 54, 66),
+    background: (0, 43, 54),
+    border: (88, 110, 117),
+};
+
+/// Saturated colors on black that stay apart in 16 colors.
+const HIGH_CONTRAST: Palette = Palette {
+    tiles: &[
+        ((0, 0, 0), (255, 255, 255)),
+        ((0, 0, 0), (255, 255, 0)),
+        ((0, 0, 0), (0, 255, 255)),
+        ((0, 0, 0), (0, 255, 0)),
+        ((0, 0, 0), (255, 0, 255)),
+        ((255, 255, 255), (255, 0, 0)),
+        ((255, 255, 255), (0, 0, 238)),
+        ((0, 0, 0), (205, 205, 0)),
+        ((0, 0, 0), (0, 205, 205)),
+        ((0, 0, 0), (0, 205, 0)),
+        ((0, 0, 0), (205, 0, 205)),
+        ((255, 255, 255), (205, 0, 0)),
+    ],
+    empty: (0, 0, 0),
+    background: (0, 0, 0),
+    border: (255, 255, 255),
+};
+
+/// The Okabe-Ito colors, alternating light and dark so that neighbors
+/// differ in lightness too.
+const DEUTERANOPIA: Palette = Palette {
+    tiles: &[
+        ((0, 0, 0), (230, 230, 230)),
+        ((0, 0, 0), (86, 180, 233)),
+        ((0, 0, 0), (230, 159, 0)),
+        ((255, 255, 255), (0, 114, 178)),
+        ((0, 0, 0), (240, 228, 66)),
+        ((255, 255, 255), (213, 94, 0)),
+        ((0, 0, 0), (204, 121, 167)),
+        ((255, 255, 255), (0, 158, 115)),
+        ((255, 255, 255), (40, 40, 120)),
+        ((0, 0, 0), (255, 200, 140)),
+        ((255, 255, 255), (120, 60, 0)),
+        ((255, 255, 255), (70, 20, 90)),
+    ],
+    empty: (45, 45, 45),
+    background: (25, 25, 25),
+    border: (150, 150, 150),
+};
+
+/// Blues and yellows, which protanopes see apart, with reds avoided
+/// since they look dark.
+const PROTANOPIA: Palette = Palette {
+    tiles: &[
+        ((0, 0, 0), (225, 225, 225)),
+        ((0, 0, 0), (150, 200, 255)),
+        ((0, 0, 0), (255, 225, 100)),
+        ((255, 255, 255), (30, 90, 200)),
+        ((0, 0, 0), (200, 170, 0)),
+        ((255, 255, 255), (0, 40, 110)),
+        ((0, 0, 0), (255, 245, 190)),
+        ((255, 255, 255), (110, 90, 0)),
+        ((0, 0, 0), (90, 160, 230)),
+        ((255, 255, 255), (90, 90, 90)),
+        ((0, 0, 0), (255, 190, 60)),
+        ((255, 255, 255), (60, 40, 120)),
+    ],
+    empty: (45, 45, 45),
+    background: (25, 25, 25),
+    border: (150, 150, 150),
+};
+
+/// Reds and teals, which tritanopes see apart, with blue against
+/// yellow avoided.
+const TRITANOPIA: Palette = Palette {
+    tiles: &[
+        ((0, 0, 0), (230, 230, 230)),
+        ((0, 0, 0), (255, 170, 170)),
+        ((255, 255, 255), (0, 140, 140)),
+        ((255, 255, 255), (200, 0, 0)),
+        ((0, 0, 0), (120, 220, 220)),
+        ((255, 255, 255), (140, 0, 40)),
+        ((255, 255, 255), (0, 80, 90)),
+        ((0, 0, 0), (255, 110, 110)),
+        ((255, 255, 255), (70, 70, 70)),
+        ((0, 0, 0), (255, 215, 215)),
+        ((0, 0, 0), (0, 200, 200)),
+        ((255, 255, 255), (90, 0, 0)),
+    ],
+    empty: (45, 45, 45),
+    background: (25, 25, 25),
+    border: (150, 150, 150),
+};
+
+/// Shapes telling tiles apart without color, from 2 up.
+const GLYPHS: [char; 12] = ['●', '▲', '■', '◆', '▼', '◀', '▶', '○', '△', '□', '◇', '✚'];
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum ColorDepth {
+    #[default]
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+/// The 16 ANSI colors as xterm draws them by default.
+const ANSI16: [(Color, Rgb); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Levels of each channel in the 6x6x6 cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    /// Guesses the depth from `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// The closest color to `rgb` the terminal can show.
+    pub fn color(self, rgb: Rgb) -> Color {
+        match self {
+            ColorDepth::TrueColor => Color::Rgb(rgb.0, rgb.1, rgb.2),
+            ColorDepth::Ansi256 => {
+                let level = |channel: u8| {
+                    (0..6)
+                        .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(channel))
+                        .unwrap()
+                };
+                let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+                let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+                // the gray ramp 232..=255 runs from 8 to 238 in steps of 10
+                let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+                let step = (average.saturating_sub(3) / 10).min(23) as u8;
+                let gray = 8 + 10 * step;
+                if distance(rgb, (gray, gray, gray)) < distance(rgb, cube) {
+                    Color::Indexed(232 + step)
+                } else {
+                    Color::Indexed(16 + 36 * r as u8 + 6 * g as u8 + b as u8)
+                }
+            }
+            ColorDepth::Ansi16 => {
+                ANSI16
+                    .iter()
+                    .min_by_key(|&&(_, ansi)| distance(rgb, ansi))
+                    .unwrap()
+                    .0
+            }
+        }
+    }
+}
+
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    let d = |x: u8, y: u8| (x.abs_diff(y) as u32).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+impl fmt::Display for ColorDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorDepth::TrueColor => "truecolor",
+            ColorDepth::Ansi256 => "256",
+            ColorDepth::Ansi16 => "16",
+        })
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "truecolor" | "24bit" => ColorDepth::TrueColor,
+            "256" => ColorDepth::Ansi256,
+            "16" => ColorDepth::Ansi16,
+            _ => bail!("unknown color depth: {s} (expected truecolor, 256 or 16)"),
+        })
+    }
+}
+
+/// A theme as drawn on a terminal of some color depth.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Colors {
+    pub theme: Theme,
+    pub depth: ColorDepth,
+    /// Also tells tiles apart by a glyph and text style, so the board
+    /// reads without color.
+    pub markers: bool,
+}
+
+impl Colors {
+    /// Foreground and background of a tile.
+    pub fn tile(&self, exponent: u8) -> (Color, Color) {
+        let (fg, bg) = self.theme.palette().tile(exponent);
+        (self.depth.color(fg), self.depth.color(bg))
+    }
+
+    /// The glyph and text style marking a tile, when markers are on.
+    pub fn marker(&self, exponent: u8) -> Option<(char, Modifier)> {
+        if !self.markers {
+            return None;
+        }
+        let index = (exponent.max(1) - 1) as usize;
+        // past the last glyph, the style tells the tiles apart
+        let modifier = match (index / GLYPHS.len(), index % 2) {
+            (0, 0) => Modifier::BOLD,
+            (0, _) => Modifier::UNDERLINED,
+            _ => Modifier::BOLD | Modifier::UNDERLINED | Modifier::ITALIC,
+        };
+        Some((GLYPHS[index % GLYPHS.len()], modifier))
+    }
+
+    pub fn empty(&self) -> Color {
+        self.depth.color(self.theme.palette().empty)
+    }
+
+    pub fn background(&self) -> Color {
+        self.depth.color(self.theme.palette().background)
+    }
+
+    pub fn border(&self) -> Color {
+        self.depth.color(self.theme.palette().border)
+    }
+}